@@ -0,0 +1,37 @@
+use std::io::Write;
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+
+/// Streams record batches from the `*_to_record_batch` helpers into a single
+/// Snappy-compressed Parquet file. Call `close` to write the footer.
+pub struct ParquetArchiveWriter<W: Write + Send> {
+    inner: ArrowWriter<W>,
+}
+
+impl<W: Write + Send> ParquetArchiveWriter<W> {
+    pub fn new(writer: W, schema: SchemaRef) -> Result<Self, ParquetError> {
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(Self {
+            inner: ArrowWriter::try_new(writer, schema, Some(props))?,
+        })
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<(), ParquetError> {
+        self.inner.write(batch)
+    }
+
+    pub fn flush(&mut self) -> Result<(), ParquetError> {
+        self.inner.flush()
+    }
+
+    pub fn close(self) -> Result<(), ParquetError> {
+        self.inner.close().map(|_| ())
+    }
+}
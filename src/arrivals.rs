@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::geo::haversine_meters;
+use crate::snapshot::VehicleSnapshot;
+use crate::types::{StopData, VehicleData};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopEvent {
+    Arrived {
+        vehicle_id: String,
+        route_id: String,
+        stop_id: String,
+        at: DateTime<Utc>,
+    },
+    Departed {
+        vehicle_id: String,
+        route_id: String,
+        stop_id: String,
+        arrived_at: DateTime<Utc>,
+        at: DateTime<Utc>,
+        dwell: Duration,
+    },
+}
+
+struct StopZone {
+    stop_id: String,
+    latitude: f64,
+    longitude: f64,
+    radius: Option<f64>,
+}
+
+struct Visit {
+    route_id: String,
+    stop_id: String,
+    arrived_at: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+}
+
+/// Turns a stream of vehicle positions into stop arrival and departure events.
+///
+/// A vehicle arrives when it comes within a stop's `radius` on its current `route_id`, and
+/// departs once it is more than `radius + exit_margin` away, changes route, reaches another
+/// stop, or stops reporting for `stale_after`. Departures are stamped at the last
+/// observation at the stop, so dwell times cover only the polls that saw the vehicle there.
+pub struct ArrivalDetector {
+    stops_by_route: HashMap<String, Vec<StopZone>>,
+    visits: HashMap<String, Visit>,
+    default_radius: f64,
+    exit_margin: f64,
+    stale_after: Duration,
+}
+
+impl ArrivalDetector {
+    pub fn new(stops: &[StopData]) -> Self {
+        let mut detector = Self {
+            stops_by_route: HashMap::new(),
+            visits: HashMap::new(),
+            default_radius: 30.0,
+            exit_margin: 10.0,
+            stale_after: Duration::from_secs(300),
+        };
+        detector.set_stops(stops);
+        detector
+    }
+
+    /// Radius in meters for stops that do not report one.
+    pub fn default_radius(mut self, meters: f64) -> Self {
+        self.default_radius = meters;
+        self
+    }
+
+    pub fn exit_margin(mut self, meters: f64) -> Self {
+        self.exit_margin = meters;
+        self
+    }
+
+    pub fn stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    pub fn set_stops(&mut self, stops: &[StopData]) {
+        self.stops_by_route.clear();
+        for stop in stops {
+            let (Some(latitude), Some(longitude)) = (stop.latitude, stop.longitude) else {
+                continue;
+            };
+            for route_id in stop.routes_and_positions.keys() {
+                self.stops_by_route
+                    .entry(route_id.clone())
+                    .or_default()
+                    .push(StopZone {
+                        stop_id: stop.id.clone(),
+                        latitude,
+                        longitude,
+                        radius: stop.radius.filter(|r| *r > 0.0),
+                    });
+            }
+        }
+    }
+
+    /// Feeds one full `get_buses` result. Vehicles missing from it for longer than
+    /// `stale_after` have their open visit closed at the time they were last seen.
+    pub fn update(
+        &mut self,
+        fetched_at: DateTime<Utc>,
+        vehicles: &[VehicleData],
+    ) -> Vec<StopEvent> {
+        let mut events = Vec::new();
+        for vehicle in vehicles {
+            events.extend(self.observe(fetched_at, vehicle));
+        }
+
+        let stale: Vec<String> = self
+            .visits
+            .iter()
+            .filter(|(_, v)| {
+                (fetched_at - v.last_seen)
+                    .to_std()
+                    .is_ok_and(|age| age > self.stale_after)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for vehicle_id in stale {
+            if let Some(visit) = self.visits.remove(&vehicle_id) {
+                let at = visit.last_seen;
+                events.push(departed(vehicle_id, visit, at));
+            }
+        }
+        events
+    }
+
+    pub fn ingest(&mut self, snapshot: &VehicleSnapshot) -> Vec<StopEvent> {
+        self.observe(snapshot.fetched_at, &snapshot.vehicle)
+    }
+
+    fn observe(&mut self, at: DateTime<Utc>, vehicle: &VehicleData) -> Vec<StopEvent> {
+        let mut events = Vec::new();
+        let position = match (vehicle.latitude, vehicle.longitude) {
+            (Some(lat), Some(lon)) if !vehicle.out_of_service.unwrap_or(false) => Some((lat, lon)),
+            _ => None,
+        };
+        let route_id = vehicle.route_id.as_deref().unwrap_or_default();
+        let default_radius = self.default_radius;
+        let exit_margin = self.exit_margin;
+        let zones = self
+            .stops_by_route
+            .get(route_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        if let Some(visit) = self.visits.get_mut(&vehicle.id) {
+            let still_there = position.is_some_and(|(lat, lon)| {
+                visit.route_id == route_id
+                    && zones.iter().any(|z| {
+                        z.stop_id == visit.stop_id
+                            && haversine_meters(lat, lon, z.latitude, z.longitude)
+                                <= z.radius.unwrap_or(default_radius) + exit_margin
+                    })
+            });
+            if still_there {
+                visit.last_seen = at;
+                return events;
+            }
+            if let Some(visit) = self.visits.remove(&vehicle.id) {
+                let left_at = visit.last_seen;
+                events.push(departed(vehicle.id.clone(), visit, left_at));
+            }
+        }
+
+        let Some((lat, lon)) = position else {
+            return events;
+        };
+        let nearest = zones
+            .iter()
+            .map(|z| (z, haversine_meters(lat, lon, z.latitude, z.longitude)))
+            .filter(|(z, d)| *d <= z.radius.unwrap_or(default_radius))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((zone, _)) = nearest {
+            self.visits.insert(
+                vehicle.id.clone(),
+                Visit {
+                    route_id: route_id.to_string(),
+                    stop_id: zone.stop_id.clone(),
+                    arrived_at: at,
+                    last_seen: at,
+                },
+            );
+            events.push(StopEvent::Arrived {
+                vehicle_id: vehicle.id.clone(),
+                route_id: route_id.to_string(),
+                stop_id: zone.stop_id.clone(),
+                at,
+            });
+        }
+        events
+    }
+}
+
+fn departed(vehicle_id: String, visit: Visit, at: DateTime<Utc>) -> StopEvent {
+    StopEvent::Departed {
+        vehicle_id,
+        route_id: visit.route_id,
+        stop_id: visit.stop_id,
+        arrived_at: visit.arrived_at,
+        at,
+        dwell: (at - visit.arrived_at).to_std().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // About 11.1 m per 0.0001° of latitude.
+    const STOP_LAT: f64 = 41.0;
+    const STOP_LON: f64 = -87.0;
+
+    fn stop(id: &str, latitude: f64, routes: &[&str]) -> StopData {
+        StopData {
+            id: id.to_string(),
+            latitude: Some(latitude),
+            longitude: Some(STOP_LON),
+            routes_and_positions: routes.iter().map(|r| (r.to_string(), vec![0.0])).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn bus(route: &str, meters_north: f64) -> VehicleData {
+        VehicleData {
+            id: "bus-1".to_string(),
+            route_id: Some(route.to_string()),
+            latitude: Some(STOP_LAT + meters_north / 111_195.0),
+            longitude: Some(STOP_LON),
+            ..Default::default()
+        }
+    }
+
+    fn t(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_741_000_000 + seconds, 0).unwrap()
+    }
+
+    fn arrived(route: &str, stop: &str, at: DateTime<Utc>) -> StopEvent {
+        StopEvent::Arrived {
+            vehicle_id: "bus-1".to_string(),
+            route_id: route.to_string(),
+            stop_id: stop.to_string(),
+            at,
+        }
+    }
+
+    fn departed(
+        route: &str,
+        stop: &str,
+        arrived_at: DateTime<Utc>,
+        at: DateTime<Utc>,
+    ) -> StopEvent {
+        StopEvent::Departed {
+            vehicle_id: "bus-1".to_string(),
+            route_id: route.to_string(),
+            stop_id: stop.to_string(),
+            arrived_at,
+            at,
+            dwell: (at - arrived_at).to_std().unwrap(),
+        }
+    }
+
+    #[test]
+    fn arrival_dwell_and_departure() {
+        let mut detector = ArrivalDetector::new(&[stop("A", STOP_LAT, &["1"])]);
+        assert_eq!(detector.update(t(0), &[bus("1", 80.0)]), []);
+        assert_eq!(
+            detector.update(t(10), &[bus("1", 5.0)]),
+            [arrived("1", "A", t(10))]
+        );
+        assert_eq!(detector.update(t(20), &[bus("1", 0.0)]), []);
+        // Departures are stamped at the last poll that still saw the bus at the stop.
+        assert_eq!(
+            detector.update(t(30), &[bus("1", 80.0)]),
+            [departed("1", "A", t(10), t(20))]
+        );
+    }
+
+    #[test]
+    fn exit_margin_keeps_visit_open() {
+        let mut detector = ArrivalDetector::new(&[stop("A", STOP_LAT, &["1"])])
+            .default_radius(30.0)
+            .exit_margin(10.0);
+        // 35 m is outside the radius, so approaching there is not an arrival...
+        assert_eq!(detector.update(t(0), &[bus("1", 35.0)]), []);
+        assert_eq!(
+            detector.update(t(10), &[bus("1", 20.0)]),
+            [arrived("1", "A", t(10))]
+        );
+        // ...but drifting back out to it does not end the visit.
+        assert_eq!(detector.update(t(20), &[bus("1", 35.0)]), []);
+        assert_eq!(detector.update(t(30), &[bus("1", 25.0)]), []);
+        assert_eq!(
+            detector.update(t(40), &[bus("1", 45.0)]),
+            [departed("1", "A", t(10), t(30))]
+        );
+    }
+
+    #[test]
+    fn moving_to_the_next_stop_closes_the_visit() {
+        let mut detector = ArrivalDetector::new(&[
+            stop("A", STOP_LAT, &["1"]),
+            stop("B", STOP_LAT + 0.001, &["1"]),
+        ]);
+        detector.update(t(0), &[bus("1", 0.0)]);
+        assert_eq!(
+            detector.update(t(60), &[bus("1", 111.0)]),
+            [departed("1", "A", t(0), t(0)), arrived("1", "B", t(60)),]
+        );
+    }
+
+    #[test]
+    fn route_change_departs_and_rearrives() {
+        let mut detector = ArrivalDetector::new(&[stop("A", STOP_LAT, &["1", "2"])]);
+        detector.update(t(0), &[bus("1", 0.0)]);
+        detector.update(t(10), &[bus("1", 0.0)]);
+        assert_eq!(
+            detector.update(t(20), &[bus("2", 0.0)]),
+            [departed("1", "A", t(0), t(10)), arrived("2", "A", t(20)),]
+        );
+    }
+
+    #[test]
+    fn stale_vehicles_depart_when_last_seen() {
+        let mut detector = ArrivalDetector::new(&[stop("A", STOP_LAT, &["1"])])
+            .stale_after(Duration::from_secs(120));
+        detector.update(t(0), &[bus("1", 0.0)]);
+        detector.update(t(30), &[bus("1", 0.0)]);
+        assert_eq!(detector.update(t(120), &[]), []);
+        assert_eq!(
+            detector.update(t(160), &[]),
+            [departed("1", "A", t(0), t(30))]
+        );
+        assert_eq!(detector.update(t(400), &[]), []);
+    }
+
+    #[test]
+    fn out_of_service_vehicles_leave_the_stop() {
+        let mut detector = ArrivalDetector::new(&[stop("A", STOP_LAT, &["1"])]);
+        detector.update(t(0), &[bus("1", 0.0)]);
+        let parked = VehicleData {
+            out_of_service: Some(true),
+            ..bus("1", 0.0)
+        };
+        assert_eq!(
+            detector.update(t(10), &[parked]),
+            [departed("1", "A", t(0), t(0))]
+        );
+    }
+}
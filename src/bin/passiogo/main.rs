@@ -0,0 +1,377 @@
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use passiogo_rs::{PassioGoClient, StopData, truncate_with_ellipsis};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[cfg(feature = "tui")]
+mod watch;
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(name = "passiogo", about = "Query Passio GO transit systems", version)]
+struct Cli {
+    #[arg(short, long, value_enum, default_value_t = Format::Table, global = true)]
+    format: Format,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List transit systems, optionally filtered by name
+    Systems { query: Option<String> },
+    /// List the routes of a system
+    Routes { system: String },
+    /// List the stops of a system
+    Stops {
+        system: String,
+        #[arg(short, long)]
+        route: Option<String>,
+    },
+    /// List the vehicles currently reporting in a system
+    Buses {
+        system: String,
+        #[arg(short, long)]
+        route: Option<String>,
+        #[arg(long)]
+        include_out_of_service: bool,
+    },
+    /// List the active alerts of a system
+    Alerts { system: String },
+    /// Show arrival estimates for a stop
+    Etas {
+        system: String,
+        stop: String,
+        #[arg(short, long)]
+        route: Option<String>,
+    },
+    /// Live dashboard of a system's vehicles and alerts
+    #[cfg(feature = "tui")]
+    Watch {
+        system: String,
+        #[arg(short, long)]
+        route: Option<String>,
+        /// Seconds between refreshes
+        #[arg(short, long, default_value_t = 5)]
+        interval: u64,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let client = PassioGoClient::new();
+    let format = cli.format;
+
+    match cli.command {
+        Command::Systems { query } => {
+            let systems: Vec<_> = client
+                .find_systems(query.as_deref().unwrap_or(""))
+                .await?
+                .into_iter()
+                .map(|m| m.system)
+                .collect();
+            print_records(
+                to_rows(&systems)?,
+                &["id", "name", "username", "go_agency_name", "homepage"],
+                format,
+            )
+        }
+        Command::Routes { system } => {
+            let system_id = resolve_system(&client, &system).await?;
+            let routes = client.get_routes(system_id).await?;
+            print_records(
+                to_rows(&routes)?,
+                &[
+                    "id",
+                    "short_name",
+                    "name",
+                    "group_color",
+                    "service_time_short",
+                    "outdated",
+                ],
+                format,
+            )
+        }
+        Command::Stops { system, route } => {
+            let system_id = resolve_system(&client, &system).await?;
+            let mut stops = client.get_stops(system_id).await?;
+            if let Some(route) = route {
+                stops.retain(|s| s.routes_and_positions.contains_key(&route));
+            }
+            stops.sort_by(|a, b| a.name.cmp(&b.name));
+            print_records(
+                to_rows(&stops)?,
+                &[
+                    "id",
+                    "name",
+                    "latitude",
+                    "longitude",
+                    "routes_and_positions",
+                ],
+                format,
+            )
+        }
+        Command::Buses {
+            system,
+            route,
+            include_out_of_service,
+        } => {
+            let system_id = resolve_system(&client, &system).await?;
+            let mut buses = client.get_buses(system_id).await?;
+            if let Some(route) = route {
+                buses.retain(|b| b.route_id.as_deref() == Some(route.as_str()));
+            }
+            if !include_out_of_service {
+                buses.retain(|b| !b.out_of_service.unwrap_or(false));
+            }
+            buses.sort_by(|a, b| a.route_name.cmp(&b.route_name).then(a.name.cmp(&b.name)));
+            print_records(
+                to_rows(&buses)?,
+                &[
+                    "id",
+                    "name",
+                    "route_name",
+                    "latitude",
+                    "longitude",
+                    "speed",
+                    "pax_load",
+                    "created",
+                ],
+                format,
+            )
+        }
+        Command::Alerts { system } => {
+            let system_id = resolve_system(&client, &system).await?;
+            let alerts = client.get_alerts(system_id).await?;
+            let mut rows = to_rows(&alerts)?;
+            for (row, alert) in rows.iter_mut().zip(&alerts) {
+                row.insert("text".to_string(), alert.text(None).into());
+            }
+            print_records(
+                rows,
+                &["id", "name", "route_id", "important", "from_f", "text"],
+                format,
+            )
+        }
+        Command::Etas {
+            system,
+            stop,
+            route,
+        } => {
+            let system_id = resolve_system(&client, &system).await?;
+            let stops = client.get_stops(system_id).await?;
+            let stop = resolve_stop(&stops, &stop)?;
+
+            let mut rows = Vec::new();
+            for (route_id, positions) in &stop.routes_and_positions {
+                if route.as_ref().is_some_and(|r| r != route_id) {
+                    continue;
+                }
+                let Some(position) = positions.first() else {
+                    continue;
+                };
+                let etas = client
+                    .get_etas(&stop.id, route_id, position, &system_id)
+                    .await?;
+                let mut eta_rows = to_rows(&etas)?;
+                for row in eta_rows.iter_mut() {
+                    row.insert("stop_id".to_string(), stop.id.clone().into());
+                }
+                rows.extend(eta_rows);
+            }
+            print_records(
+                rows,
+                &[
+                    "route_id",
+                    "bus_name",
+                    "eta",
+                    "eta_note",
+                    "out_of_service",
+                    "reason",
+                ],
+                format,
+            )
+        }
+        #[cfg(feature = "tui")]
+        Command::Watch {
+            system,
+            route,
+            interval,
+        } => {
+            let system_id = resolve_system(&client, &system).await?;
+            watch::run(
+                client,
+                system_id,
+                system,
+                route,
+                std::time::Duration::from_secs(interval.max(1)),
+            )
+            .await
+        }
+    }
+}
+
+async fn resolve_system(client: &PassioGoClient, system: &str) -> CliResult<i64> {
+    if let Ok(id) = system.parse::<i64>() {
+        return Ok(id);
+    }
+
+    let matches = client.find_systems(system).await?;
+    match matches.as_slice() {
+        [] => Err(format!("no system matches '{}'", system).into()),
+        [best, ..] if best.score >= 1.0 => Ok(best.system.id),
+        [only] => Ok(only.system.id),
+        [best, second, ..] if best.score - second.score >= 0.2 => Ok(best.system.id),
+        many => {
+            let names = many
+                .iter()
+                .take(10)
+                .map(|m| {
+                    format!(
+                        "  {} ({})",
+                        m.system.name.as_deref().unwrap_or("?"),
+                        m.system.id
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Err(format!("'{}' matches {} systems:\n{}", system, many.len(), names).into())
+        }
+    }
+}
+
+fn resolve_stop<'a>(stops: &'a [StopData], stop: &str) -> CliResult<&'a StopData> {
+    if let Some(found) = stops.iter().find(|s| s.id == stop) {
+        return Ok(found);
+    }
+
+    let query = stop.to_lowercase();
+    let candidates: Vec<_> = stops
+        .iter()
+        .filter(|s| {
+            s.name
+                .as_ref()
+                .is_some_and(|n| n.to_lowercase().contains(&query))
+        })
+        .collect();
+
+    match candidates.as_slice() {
+        [] => Err(format!("no stop matches '{}'", stop).into()),
+        [only] => Ok(only),
+        many => {
+            let names = many
+                .iter()
+                .take(10)
+                .map(|s| format!("  {} ({})", s.name.as_deref().unwrap_or("?"), s.id))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Err(format!("'{}' matches {} stops:\n{}", stop, many.len(), names).into())
+        }
+    }
+}
+
+fn to_rows<T: Serialize>(records: &[T]) -> CliResult<Vec<Map<String, Value>>> {
+    records
+        .iter()
+        .map(|r| match serde_json::to_value(r)? {
+            Value::Object(map) => Ok(map),
+            other => Err(format!("unexpected record shape: {}", other).into()),
+        })
+        .collect()
+}
+
+fn print_records(rows: Vec<Map<String, Value>>, columns: &[&str], format: Format) -> CliResult<()> {
+    match format {
+        Format::Json => {
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        }
+        Format::Csv => {
+            println!("{}", csv_line(columns.iter().map(|c| c.to_string())));
+            for row in &rows {
+                println!("{}", csv_line(columns.iter().map(|c| cell(row.get(*c)))));
+            }
+        }
+        Format::Table => {
+            if rows.is_empty() {
+                eprintln!("(no results)");
+                return Ok(());
+            }
+            let cells: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .map(|c| truncate_with_ellipsis(&cell(row.get(*c)), 60))
+                        .collect()
+                })
+                .collect();
+            let mut widths: Vec<usize> = columns.iter().map(|c| c.len()).collect();
+            for row in &cells {
+                for (w, c) in widths.iter_mut().zip(row) {
+                    *w = (*w).max(c.chars().count());
+                }
+            }
+
+            let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+            println!("{}", table_line(&header, &widths));
+            for row in &cells {
+                println!("{}", table_line(row, &widths));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.replace('\n', " "),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn table_line(cells: &[String], widths: &[usize]) -> String {
+    cells
+        .iter()
+        .zip(widths)
+        .map(|(c, w)| format!("{:<width$}", c, width = w))
+        .collect::<Vec<_>>()
+        .join("  ")
+        .trim_end()
+        .to_string()
+}
+
+fn csv_line(cells: impl Iterator<Item = String>) -> String {
+    cells
+        .map(|c| {
+            if c.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", c.replace('"', "\"\""))
+            } else {
+                c
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
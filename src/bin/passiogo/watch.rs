@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use passiogo_rs::{PassioGoClient, SystemAlertData, VehicleData, truncate_with_ellipsis};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc;
+
+use crate::CliResult;
+
+enum Update {
+    Buses(Result<Vec<VehicleData>, String>),
+    Alerts(Result<Vec<SystemAlertData>, String>),
+}
+
+struct Tracked {
+    vehicle: VehicleData,
+    last_moved: Instant,
+}
+
+struct WatchState {
+    system_label: String,
+    route_filter: Option<String>,
+    // `None` when no route names the system's timezone, so ages cannot be told.
+    timezone: Option<Tz>,
+    vehicles: HashMap<String, Tracked>,
+    alerts: Vec<SystemAlertData>,
+    last_refresh: Option<Instant>,
+    error: Option<String>,
+}
+
+impl WatchState {
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Buses(Ok(buses)) => {
+                let now = Instant::now();
+                let mut next = HashMap::new();
+                for vehicle in buses {
+                    if let Some(route) = &self.route_filter
+                        && vehicle.route_id.as_ref() != Some(route)
+                    {
+                        continue;
+                    }
+                    let last_moved = match self.vehicles.remove(&vehicle.id) {
+                        Some(prev)
+                            if prev.vehicle.latitude == vehicle.latitude
+                                && prev.vehicle.longitude == vehicle.longitude =>
+                        {
+                            prev.last_moved
+                        }
+                        _ => now,
+                    };
+                    next.insert(
+                        vehicle.id.clone(),
+                        Tracked {
+                            vehicle,
+                            last_moved,
+                        },
+                    );
+                }
+                self.vehicles = next;
+                self.last_refresh = Some(now);
+                self.error = None;
+            }
+            Update::Alerts(Ok(alerts)) => {
+                self.alerts = alerts
+                    .into_iter()
+                    .filter(|a| !a.archive.unwrap_or(false))
+                    .collect();
+            }
+            Update::Buses(Err(e)) | Update::Alerts(Err(e)) => self.error = Some(e),
+        }
+    }
+}
+
+pub async fn run(
+    client: PassioGoClient,
+    system_id: i64,
+    system_label: String,
+    route_filter: Option<String>,
+    interval: Duration,
+) -> CliResult<()> {
+    // `created` times are local to the system; its routes carry the timezone.
+    let timezone = client.get_routes(system_id).await.ok().and_then(|routes| {
+        routes
+            .iter()
+            .find_map(|r| r.timezone.as_deref()?.trim().parse::<Tz>().ok())
+    });
+
+    let (tx, mut updates) = mpsc::channel(4);
+    let poller = tokio::spawn(async move {
+        loop {
+            let buses = client.get_buses(system_id).await.map_err(|e| e.to_string());
+            let alerts = client
+                .get_alerts(system_id)
+                .await
+                .map_err(|e| e.to_string());
+            if tx.send(Update::Buses(buses)).await.is_err()
+                || tx.send(Update::Alerts(alerts)).await.is_err()
+            {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    // Terminal input blocks, so it is read on a blocking thread that stops once the
+    // receiver is dropped.
+    let (key_tx, mut keys) = mpsc::channel(16);
+    tokio::task::spawn_blocking(move || read_keys(key_tx));
+
+    let mut state = WatchState {
+        system_label,
+        route_filter,
+        timezone,
+        vehicles: HashMap::new(),
+        alerts: Vec::new(),
+        last_refresh: None,
+        error: None,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut state, &mut updates, &mut keys).await;
+    ratatui::restore();
+    poller.abort();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    state: &mut WatchState,
+    updates: &mut mpsc::Receiver<Update>,
+    keys: &mut mpsc::Receiver<io::Result<KeyEvent>>,
+) -> CliResult<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, state))?;
+
+        // Redraw at least once a second so the ages keep counting.
+        tokio::select! {
+            Some(update) = updates.recv() => state.apply(update),
+            key = keys.recv() => match key {
+                Some(Ok(key)) if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) => {
+                    return Ok(());
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => return Ok(()),
+            },
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+    }
+}
+
+fn read_keys(tx: mpsc::Sender<io::Result<KeyEvent>>) {
+    while !tx.is_closed() {
+        let key = match event::poll(Duration::from_millis(250)) {
+            Ok(false) => continue,
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => Ok(key),
+                Ok(_) => continue,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        let failed = key.is_err();
+        if tx.blocking_send(key).is_err() || failed {
+            return;
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, state: &WatchState) {
+    let [header, body] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .areas(frame.area());
+    let [buses_area, alerts_area] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+        .areas(body);
+
+    let in_service = state
+        .vehicles
+        .values()
+        .filter(|t| !t.vehicle.out_of_service.unwrap_or(false))
+        .count();
+    let refreshed = match state.last_refresh {
+        Some(at) => format!("updated {} ago", format_age(at.elapsed())),
+        None => "loading…".to_string(),
+    };
+    let mut status = vec![
+        Span::styled(
+            state.system_label.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(
+            "  {} in service / {} reporting  {}  (q to quit)",
+            in_service,
+            state.vehicles.len(),
+            refreshed
+        )),
+    ];
+    if let Some(error) = &state.error {
+        status.push(Span::styled(
+            format!("  error: {}", error),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    frame.render_widget(
+        Paragraph::new(Line::from(status)).block(Block::default().borders(Borders::ALL)),
+        header,
+    );
+
+    frame.render_widget(buses_table(state), buses_area);
+
+    let alerts: Vec<ListItem> = state
+        .alerts
+        .iter()
+        .map(|alert| {
+            let title_style = if alert.important.unwrap_or(false) {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().add_modifier(Modifier::BOLD)
+            };
+            let mut lines = vec![Line::styled(
+                alert.name.clone().unwrap_or_default(),
+                title_style,
+            )];
+            if let Some(text) = alert.text(Some(240)) {
+                lines.extend(text.lines().map(|l| Line::raw(l.to_string())));
+            }
+            lines.push(Line::raw(""));
+            ListItem::new(lines)
+        })
+        .collect();
+    let alerts_block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Alerts ({})", state.alerts.len()));
+    if alerts.is_empty() {
+        frame.render_widget(
+            Paragraph::new("No active alerts")
+                .wrap(Wrap { trim: true })
+                .block(alerts_block),
+            alerts_area,
+        );
+    } else {
+        frame.render_widget(List::new(alerts).block(alerts_block), alerts_area);
+    }
+}
+
+fn buses_table(state: &WatchState) -> Table<'_> {
+    let mut by_route: BTreeMap<String, Vec<&Tracked>> = BTreeMap::new();
+    for tracked in state.vehicles.values() {
+        let route = tracked
+            .vehicle
+            .route_name
+            .clone()
+            .unwrap_or_else(|| "(no route)".to_string());
+        by_route.entry(route).or_default().push(tracked);
+    }
+
+    let mut rows = Vec::new();
+    for (route, mut vehicles) in by_route {
+        vehicles.sort_by(|a, b| a.vehicle.name.cmp(&b.vehicle.name));
+        let color = vehicles
+            .first()
+            .and_then(|t| t.vehicle.color.as_deref())
+            .and_then(parse_hex_color)
+            .unwrap_or(Color::White);
+        rows.push(
+            Row::new(vec![Cell::from(format!("{} ({})", route, vehicles.len()))])
+                .style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
+        );
+        for tracked in vehicles {
+            let v = &tracked.vehicle;
+            let out_of_service = v.out_of_service.unwrap_or(false);
+            let row = Row::new(vec![
+                Cell::from(format!(
+                    "  {}",
+                    truncate_with_ellipsis(v.name.as_deref().unwrap_or(&v.id), 20)
+                )),
+                Cell::from(v.speed.map(|s| format!("{:.0}", s)).unwrap_or_default()),
+                Cell::from(v.pax_load.map(|p| format!("{:.0}", p)).unwrap_or_default()),
+                Cell::from(
+                    v.calculated_course
+                        .map(|c| format!("{:.0}°", c))
+                        .unwrap_or_default(),
+                ),
+                Cell::from(match state.timezone {
+                    Some(timezone) => v
+                        .reported_at(timezone)
+                        .map(|at| format_age((Utc::now() - at).to_std().unwrap_or_default()))
+                        .unwrap_or_default(),
+                    None => "?".to_string(),
+                }),
+                Cell::from(format_age(tracked.last_moved.elapsed())),
+                Cell::from(if out_of_service { "out of service" } else { "" }),
+            ]);
+            rows.push(if out_of_service {
+                row.style(Style::default().fg(Color::DarkGray))
+            } else {
+                row
+            });
+        }
+    }
+
+    Table::new(
+        rows,
+        [
+            Constraint::Min(24),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(15),
+        ],
+    )
+    .header(
+        Row::new(vec!["Bus", "Speed", "Load", "Heading", "Age", "Moved", ""])
+            .style(Style::default().add_modifier(Modifier::UNDERLINED)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Vehicles"))
+}
+
+fn parse_hex_color(s: &str) -> Option<Color> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    BooleanBuilder, Float64Builder, Int64Builder, ListBuilder, StringBuilder,
+    TimestampMillisecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
+
+use crate::snapshot::VehicleSnapshot;
+use crate::types::{ETAData, SystemAlertData};
+
+fn timestamp_field(name: &str) -> Field {
+    Field::new(
+        name,
+        DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        false,
+    )
+}
+
+fn timestamps(values: impl Iterator<Item = DateTime<Utc>>) -> ArrayRef {
+    let mut b = TimestampMillisecondBuilder::new().with_timezone("UTC");
+    for v in values {
+        b.append_value(v.timestamp_millis());
+    }
+    Arc::new(b.finish())
+}
+
+fn strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+    let mut b = StringBuilder::new();
+    for v in values {
+        b.append_option(v);
+    }
+    Arc::new(b.finish())
+}
+
+fn string_lists<'a>(values: impl Iterator<Item = Option<&'a [String]>>) -> ArrayRef {
+    let mut b = ListBuilder::new(StringBuilder::new());
+    for v in values {
+        match v {
+            Some(items) => {
+                for item in items {
+                    b.values().append_value(unquote(item));
+                }
+                b.append(true);
+            }
+            None => b.append(false),
+        }
+    }
+    Arc::new(b.finish())
+}
+
+// `ETAData::schedule_times` keeps each entry as raw JSON, so strings arrive quoted.
+fn unquote(value: &str) -> String {
+    serde_json::from_str::<String>(value).unwrap_or_else(|_| value.to_string())
+}
+
+fn floats(values: impl Iterator<Item = Option<f64>>) -> ArrayRef {
+    let mut b = Float64Builder::new();
+    for v in values {
+        b.append_option(v);
+    }
+    Arc::new(b.finish())
+}
+
+fn ints(values: impl Iterator<Item = Option<i64>>) -> ArrayRef {
+    let mut b = Int64Builder::new();
+    for v in values {
+        b.append_option(v);
+    }
+    Arc::new(b.finish())
+}
+
+fn bools(values: impl Iterator<Item = Option<bool>>) -> ArrayRef {
+    let mut b = BooleanBuilder::new();
+    for v in values {
+        b.append_option(v);
+    }
+    Arc::new(b.finish())
+}
+
+pub fn vehicle_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        timestamp_field("fetched_at"),
+        Field::new("system_id", DataType::Int64, false),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("type", DataType::Utf8, true),
+        Field::new("calculated_course", DataType::Float64, true),
+        Field::new("route_id", DataType::Utf8, true),
+        Field::new("route_name", DataType::Utf8, true),
+        Field::new("color", DataType::Utf8, true),
+        Field::new("created", DataType::Utf8, true),
+        Field::new("latitude", DataType::Float64, true),
+        Field::new("longitude", DataType::Float64, true),
+        Field::new("speed", DataType::Float64, true),
+        Field::new("pax_load", DataType::Float64, true),
+        Field::new("out_of_service", DataType::Boolean, true),
+        Field::new("more", DataType::Utf8, true),
+        Field::new("trip_id", DataType::Utf8, true),
+    ]))
+}
+
+pub fn vehicles_to_record_batch(snapshots: &[VehicleSnapshot]) -> Result<RecordBatch, ArrowError> {
+    let v = || snapshots.iter().map(|s| &s.vehicle);
+    RecordBatch::try_new(
+        vehicle_schema(),
+        vec![
+            timestamps(snapshots.iter().map(|s| s.fetched_at)),
+            ints(snapshots.iter().map(|s| Some(s.system_id))),
+            strings(v().map(|v| Some(v.id.as_str()))),
+            strings(v().map(|v| v.name.as_deref())),
+            strings(v().map(|v| v.r#type.as_deref())),
+            floats(v().map(|v| v.calculated_course)),
+            strings(v().map(|v| v.route_id.as_deref())),
+            strings(v().map(|v| v.route_name.as_deref())),
+            strings(v().map(|v| v.color.as_deref())),
+            strings(v().map(|v| v.created.as_deref())),
+            floats(v().map(|v| v.latitude)),
+            floats(v().map(|v| v.longitude)),
+            floats(v().map(|v| v.speed)),
+            floats(v().map(|v| v.pax_load)),
+            bools(v().map(|v| v.out_of_service)),
+            strings(v().map(|v| v.more.as_deref())),
+            strings(v().map(|v| v.trip_id.as_deref())),
+        ],
+    )
+}
+
+pub fn eta_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        timestamp_field("fetched_at"),
+        Field::new("system_id", DataType::Int64, false),
+        Field::new("stop_id", DataType::Utf8, false),
+        Field::new("route_id", DataType::Utf8, false),
+        Field::new("bus_name", DataType::Utf8, false),
+        Field::new("eta", DataType::Utf8, false),
+        Field::new("eta_note", DataType::Utf8, true),
+        Field::new("go_show_schedule", DataType::Int64, true),
+        Field::new("order", DataType::Int64, true),
+        Field::new("out_of_service", DataType::Boolean, false),
+        Field::new("reason", DataType::Utf8, false),
+        Field::new("schedule_not_empty", DataType::Int64, true),
+        Field::new("schedule_time", DataType::Utf8, true),
+        Field::new(
+            "schedule_times",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            true,
+        ),
+        Field::new("seconds_spent", DataType::Int64, false),
+    ]))
+}
+
+/// `get_etas` results carry no stop or system, so they are supplied alongside.
+/// `schedule_times` is stored as a list of strings.
+pub fn etas_to_record_batch(
+    fetched_at: DateTime<Utc>,
+    system_id: i64,
+    stop_id: &str,
+    etas: &[ETAData],
+) -> Result<RecordBatch, ArrowError> {
+    RecordBatch::try_new(
+        eta_schema(),
+        vec![
+            timestamps(etas.iter().map(|_| fetched_at)),
+            ints(etas.iter().map(|_| Some(system_id))),
+            strings(etas.iter().map(|_| Some(stop_id))),
+            strings(etas.iter().map(|e| Some(e.route_id.as_str()))),
+            strings(etas.iter().map(|e| Some(e.bus_name.as_str()))),
+            strings(etas.iter().map(|e| Some(e.eta.as_str()))),
+            strings(etas.iter().map(|e| e.eta_note.as_deref())),
+            ints(etas.iter().map(|e| e.go_show_schedule)),
+            ints(etas.iter().map(|e| e.order)),
+            bools(etas.iter().map(|e| Some(e.out_of_service))),
+            strings(etas.iter().map(|e| Some(e.reason.as_str()))),
+            ints(etas.iter().map(|e| e.schedule_not_empty)),
+            strings(etas.iter().map(|e| e.schedule_time.as_deref())),
+            string_lists(etas.iter().map(|e| e.schedule_times.as_deref())),
+            ints(etas.iter().map(|e| Some(e.seconds_spent))),
+        ],
+    )
+}
+
+pub fn alert_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        timestamp_field("fetched_at"),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("system_id", DataType::Int64, true),
+        Field::new("route_id", DataType::Utf8, true),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("html", DataType::Utf8, true),
+        Field::new("archive", DataType::Boolean, true),
+        Field::new("important", DataType::Boolean, true),
+        Field::new("date_time_created", DataType::Utf8, true),
+        Field::new("date_time_from", DataType::Utf8, true),
+        Field::new("date_time_to", DataType::Utf8, true),
+        Field::new("as_push", DataType::Boolean, true),
+        Field::new("gtfs", DataType::Boolean, true),
+        Field::new("gtfs_alert_cause_id", DataType::Int64, true),
+        Field::new("gtfs_alert_effect_id", DataType::Int64, true),
+        Field::new("gtfs_alert_url", DataType::Utf8, true),
+        Field::new("gtfs_alert_header_text", DataType::Utf8, true),
+        Field::new("gtfs_alert_description_text", DataType::Utf8, true),
+        Field::new("route_group_id", DataType::Int64, true),
+        Field::new("created_utc", DataType::Utf8, true),
+        Field::new("author_id", DataType::Int64, true),
+        Field::new("author", DataType::Utf8, true),
+        Field::new("updated", DataType::Utf8, true),
+        Field::new("update_author_id", DataType::Int64, true),
+        Field::new("update_author", DataType::Utf8, true),
+        Field::new("created_f", DataType::Utf8, true),
+        Field::new("from_f", DataType::Utf8, true),
+        Field::new("from_ok", DataType::Boolean, true),
+        Field::new("to_ok", DataType::Boolean, true),
+    ]))
+}
+
+pub fn alerts_to_record_batch(
+    fetched_at: DateTime<Utc>,
+    alerts: &[SystemAlertData],
+) -> Result<RecordBatch, ArrowError> {
+    let a = || alerts.iter();
+    RecordBatch::try_new(
+        alert_schema(),
+        vec![
+            timestamps(a().map(|_| fetched_at)),
+            strings(a().map(|x| Some(x.id.as_str()))),
+            ints(a().map(|x| x.system_id)),
+            strings(a().map(|x| x.route_id.as_deref())),
+            strings(a().map(|x| x.name.as_deref())),
+            strings(a().map(|x| x.html.as_deref())),
+            bools(a().map(|x| x.archive)),
+            bools(a().map(|x| x.important)),
+            strings(a().map(|x| x.date_time_created.as_deref())),
+            strings(a().map(|x| x.date_time_from.as_deref())),
+            strings(a().map(|x| x.date_time_to.as_deref())),
+            bools(a().map(|x| x.as_push)),
+            bools(a().map(|x| x.gtfs)),
+            ints(a().map(|x| x.gtfs_alert_cause_id)),
+            ints(a().map(|x| x.gtfs_alert_effect_id)),
+            strings(a().map(|x| x.gtfs_alert_url.as_deref())),
+            strings(a().map(|x| x.gtfs_alert_header_text.as_deref())),
+            strings(a().map(|x| x.gtfs_alert_description_text.as_deref())),
+            ints(a().map(|x| x.route_group_id)),
+            strings(a().map(|x| x.created_utc.as_deref())),
+            ints(a().map(|x| x.author_id)),
+            strings(a().map(|x| x.author.as_deref())),
+            strings(a().map(|x| x.updated.as_deref())),
+            ints(a().map(|x| x.update_author_id)),
+            strings(a().map(|x| x.update_author.as_deref())),
+            strings(a().map(|x| x.created_f.as_deref())),
+            strings(a().map(|x| x.from_f.as_deref())),
+            bools(a().map(|x| x.from_ok)),
+            bools(a().map(|x| x.to_ok)),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::Array;
+    use arrow_array::cast::AsArray;
+
+    use super::*;
+
+    #[test]
+    fn schedule_times_are_plain_strings() {
+        let etas = [
+            ETAData {
+                schedule_times: Some(vec!["\"10:05\"".to_string(), "\"10:35\"".to_string()]),
+                ..Default::default()
+            },
+            ETAData::default(),
+        ];
+        let batch = etas_to_record_batch(Utc::now(), 1, "stop", &etas).unwrap();
+        let lists = batch
+            .column_by_name("schedule_times")
+            .unwrap()
+            .as_list::<i32>();
+        let first = lists.value(0);
+        let first: Vec<Option<&str>> = first.as_string::<i32>().iter().collect();
+        assert_eq!(first, [Some("10:05"), Some("10:35")]);
+        assert!(lists.is_null(1));
+    }
+}
@@ -0,0 +1,321 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::helpers::{to_bool, to_f64, to_i64, to_string_opt};
+use crate::types::{
+    ETAData, RouteData, RouteShapeData, StopData, SystemAlertData, TransportationSystemData,
+    VehicleData,
+};
+
+/// A field that was missing (`raw` is null) or could not be read as the expected type,
+/// so it was left empty or given a default.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseWarning {
+    pub record_id: String,
+    pub field: String,
+    pub raw: Value,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.raw.is_null() {
+            write!(f, "missing `{}` on record {:?}", self.field, self.record_id)
+        } else {
+            write!(
+                f,
+                "unexpected `{}` on record {:?}: {}",
+                self.field, self.record_id, self.raw
+            )
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Keep whatever parsed and report the rest as warnings.
+    #[default]
+    Lenient,
+    /// Fail with `DecodeError::Malformed` when any field does not parse.
+    Strict,
+}
+
+#[derive(Debug, Clone)]
+pub struct Parsed<T> {
+    pub data: T,
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl<T> Parsed<T> {
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Request(ApiError),
+    Malformed(Vec<ParseWarning>),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Request(e) => write!(f, "request failed: {}", e),
+            DecodeError::Malformed(warnings) => {
+                write!(f, "{} malformed field(s)", warnings.len())?;
+                if let Some(first) = warnings.first() {
+                    write!(f, ", first: {}", first)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Request(e) => Some(e),
+            DecodeError::Malformed(_) => None,
+        }
+    }
+}
+
+impl From<ApiError> for DecodeError {
+    fn from(e: ApiError) -> Self {
+        DecodeError::Request(e)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ParseWarnings(Vec<ParseWarning>);
+
+impl ParseWarnings {
+    pub(crate) fn push(&mut self, record_id: &str, field: &str, raw: &Value) {
+        debug!(record_id, field, %raw, "unexpected field value");
+        self.0.push(ParseWarning {
+            record_id: record_id.to_string(),
+            field: field.to_string(),
+            raw: raw.clone(),
+        });
+    }
+
+    /// Reads fields of `value`, reporting them under `record_id`.
+    pub(crate) fn record<'a>(&'a mut self, record_id: &str, value: &'a Value) -> FieldReader<'a> {
+        FieldReader {
+            warnings: self,
+            record_id: record_id.to_string(),
+            value,
+        }
+    }
+
+    /// Like `record`, with the record id read from the required `id_field`.
+    pub(crate) fn record_by<'a>(&'a mut self, value: &'a Value, id_field: &str) -> FieldReader<'a> {
+        let mut reader = self.record("", value);
+        reader.record_id = reader.required_string(id_field).unwrap_or_default();
+        reader
+    }
+
+    fn finish<T>(self, data: T, mode: ParseMode) -> Result<Parsed<T>, DecodeError> {
+        if mode == ParseMode::Strict && !self.0.is_empty() {
+            return Err(DecodeError::Malformed(self.0));
+        }
+        Ok(Parsed {
+            data,
+            warnings: self.0,
+        })
+    }
+}
+
+pub(crate) struct FieldReader<'a> {
+    warnings: &'a mut ParseWarnings,
+    record_id: String,
+    value: &'a Value,
+}
+
+impl FieldReader<'_> {
+    pub(crate) fn record_id(&self) -> &str {
+        &self.record_id
+    }
+
+    pub(crate) fn string(&self, field: &str) -> Option<String> {
+        to_string_opt(self.value.get(field))
+    }
+
+    /// A field every record should carry; missing or null values are reported.
+    pub(crate) fn required_string(&mut self, field: &str) -> Option<String> {
+        let value = self.string(field);
+        if value.is_none() {
+            self.warnings.push(&self.record_id, field, &Value::Null);
+        }
+        value
+    }
+
+    pub(crate) fn required_i64(&mut self, field: &str) -> Option<i64> {
+        let raw = self.value.get(field);
+        let parsed = to_i64(raw);
+        if parsed.is_none() {
+            self.warnings
+                .push(&self.record_id, field, raw.unwrap_or(&Value::Null));
+        }
+        parsed
+    }
+
+    pub(crate) fn i64(&mut self, field: &str) -> Option<i64> {
+        self.check(field, to_i64)
+    }
+
+    pub(crate) fn f64(&mut self, field: &str) -> Option<f64> {
+        self.check(field, to_f64)
+    }
+
+    pub(crate) fn bool(&mut self, field: &str) -> Option<bool> {
+        self.check(field, to_bool)
+    }
+
+    // Missing fields, nulls and empty strings are simply absent; anything else that does
+    // not parse is reported.
+    fn check<T>(&mut self, field: &str, parse: fn(Option<&Value>) -> Option<T>) -> Option<T> {
+        let raw = self.value.get(field);
+        let parsed = parse(raw);
+        if parsed.is_none()
+            && let Some(raw) = raw
+            && !raw.is_null()
+            && raw.as_str().is_none_or(|s| !s.trim().is_empty())
+        {
+            self.warnings.push(&self.record_id, field, raw);
+        }
+        parsed
+    }
+}
+
+impl PassioGoClient {
+    pub async fn get_systems_checked(
+        &self,
+        mode: ParseMode,
+    ) -> Result<Parsed<Vec<TransportationSystemData>>, DecodeError> {
+        let mut warnings = ParseWarnings::default();
+        let data = self.fetch_systems(&mut warnings).await?;
+        warnings.finish(data, mode)
+    }
+
+    pub async fn get_alerts_checked(
+        &self,
+        system_id: i64,
+        mode: ParseMode,
+    ) -> Result<Parsed<Vec<SystemAlertData>>, DecodeError> {
+        let mut warnings = ParseWarnings::default();
+        let data = self.fetch_alerts(system_id, &mut warnings).await?;
+        warnings.finish(data, mode)
+    }
+
+    pub async fn get_routes_checked(
+        &self,
+        system_id: i64,
+        mode: ParseMode,
+    ) -> Result<Parsed<Vec<RouteData>>, DecodeError> {
+        let mut warnings = ParseWarnings::default();
+        let data = self.fetch_routes(system_id, &mut warnings).await?;
+        warnings.finish(data, mode)
+    }
+
+    pub async fn get_buses_checked(
+        &self,
+        system_id: i64,
+        mode: ParseMode,
+    ) -> Result<Parsed<Vec<VehicleData>>, DecodeError> {
+        let mut warnings = ParseWarnings::default();
+        let data = self.fetch_buses(system_id, &mut warnings).await?;
+        warnings.finish(data, mode)
+    }
+
+    pub async fn get_stops_checked(
+        &self,
+        system_id: i64,
+        mode: ParseMode,
+    ) -> Result<Parsed<Vec<StopData>>, DecodeError> {
+        let mut warnings = ParseWarnings::default();
+        let data = self.fetch_stops(system_id, &mut warnings).await?;
+        warnings.finish(data, mode)
+    }
+
+    pub async fn get_route_shapes_checked(
+        &self,
+        system_id: i64,
+        mode: ParseMode,
+    ) -> Result<Parsed<Vec<RouteShapeData>>, DecodeError> {
+        let mut warnings = ParseWarnings::default();
+        let data = self.fetch_route_shapes(system_id, &mut warnings).await?;
+        warnings.finish(data, mode)
+    }
+
+    pub async fn get_etas_checked(
+        &self,
+        stop_id: &String,
+        route_id: &String,
+        position: &f64,
+        system_id: &i64,
+        mode: ParseMode,
+    ) -> Result<Parsed<Vec<ETAData>>, DecodeError> {
+        let mut warnings = ParseWarnings::default();
+        let data = self
+            .fetch_etas(stop_id, route_id, position, system_id, &mut warnings)
+            .await?;
+        warnings.finish(data, mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn missing_required_fields_are_reported() {
+        let eta = json!({ "busName": "Bus 7", "etaText": "5 min", "routeId": 12, "order": "" });
+        let mut warnings = ParseWarnings::default();
+        let mut r = warnings.record_by(&eta, "busName");
+        assert_eq!(r.record_id(), "Bus 7");
+        assert_eq!(r.required_string("eta"), None);
+        assert_eq!(r.required_string("routeId").as_deref(), Some("12"));
+        assert_eq!(r.i64("order"), None);
+        assert_eq!(r.i64("goShowSchedule"), None);
+
+        assert_eq!(
+            warnings.0,
+            vec![ParseWarning {
+                record_id: "Bus 7".to_string(),
+                field: "eta".to_string(),
+                raw: Value::Null,
+            }]
+        );
+        assert_eq!(
+            warnings.0[0].to_string(),
+            "missing `eta` on record \"Bus 7\""
+        );
+        assert!(matches!(
+            warnings.finish((), ParseMode::Strict),
+            Err(DecodeError::Malformed(w)) if w.len() == 1
+        ));
+    }
+
+    #[test]
+    fn unparseable_values_are_reported() {
+        let system = json!({ "id": "abc", "goTestMode": "maybe", "goSharedCode": null });
+        let mut warnings = ParseWarnings::default();
+        let mut r = warnings.record_by(&system, "id");
+        assert_eq!(r.required_i64("id"), None);
+        assert_eq!(r.bool("goTestMode"), None);
+        assert_eq!(r.i64("goSharedCode"), None);
+
+        let parsed = warnings.finish((), ParseMode::Lenient).unwrap();
+        let fields: Vec<&str> = parsed.warnings.iter().map(|w| w.field.as_str()).collect();
+        assert_eq!(fields, ["id", "goTestMode"]);
+    }
+}
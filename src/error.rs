@@ -0,0 +1,39 @@
+use std::fmt;
+
+/// A Passio API request that failed to send, returned an unreadable body, or did not
+/// return JSON.
+#[derive(Debug)]
+pub enum ApiError {
+    Request(reqwest::Error),
+    InvalidJson(serde_json::Error),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Request(e) => write!(f, "request failed: {}", e),
+            ApiError::InvalidJson(e) => write!(f, "response is not valid JSON: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Request(e) => Some(e),
+            ApiError::InvalidJson(e) => Some(e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        ApiError::Request(e)
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError::InvalidJson(e)
+    }
+}
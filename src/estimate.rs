@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::matching::{MatchedRoute, RouteMatcher};
+use crate::snapshot::VehicleSnapshot;
+use crate::types::{ETAData, VehicleData};
+
+const MPH_TO_METERS_PER_SECOND: f64 = 0.44704;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EtaSource {
+    Passio,
+    Estimated,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Prediction {
+    pub source: EtaSource,
+    pub data: ETAData,
+    /// Only known for estimated predictions; Passio's own are free text in `data.eta`.
+    pub seconds_away: Option<f64>,
+}
+
+impl PassioGoClient {
+    /// Calls `get_etas`, and when that fails or comes back empty, estimates arrivals from
+    /// the live vehicle positions instead.
+    #[tracing::instrument(level = "debug", skip(self, estimator))]
+    pub async fn get_etas_with_fallback(
+        &self,
+        stop_id: &String,
+        route_id: &String,
+        position: &f64,
+        system_id: &i64,
+        estimator: &EtaEstimator,
+    ) -> Result<Vec<Prediction>, ApiError> {
+        let passio_error = match self.get_etas(stop_id, route_id, position, system_id).await {
+            Ok(etas) if !etas.is_empty() => {
+                return Ok(etas
+                    .into_iter()
+                    .map(|data| Prediction {
+                        source: EtaSource::Passio,
+                        data,
+                        seconds_away: None,
+                    })
+                    .collect());
+            }
+            Ok(_) => None,
+            Err(e) => Some(e),
+        };
+
+        match self.get_buses(*system_id).await {
+            Ok(buses) => Ok(estimator.estimate(stop_id, route_id, &buses)),
+            Err(e) => Err(passio_error.unwrap_or(e)),
+        }
+    }
+}
+
+/// Estimates arrival times from each vehicle's progress along the route shape.
+///
+/// Travel time for each stop-to-stop segment comes from speeds learned from stored
+/// snapshots where available, then the vehicle's live `speed` (for the segment it is on),
+/// then `default_speed`.
+pub struct EtaEstimator {
+    matcher: RouteMatcher,
+    segment_speeds: HashMap<(String, usize), (f64, u32)>,
+    default_speed: f64,
+    live_speed_factor: f64,
+    min_live_speed: f64,
+}
+
+impl EtaEstimator {
+    pub fn new(matcher: RouteMatcher) -> Self {
+        Self {
+            matcher,
+            segment_speeds: HashMap::new(),
+            default_speed: 5.0,
+            live_speed_factor: MPH_TO_METERS_PER_SECOND,
+            min_live_speed: 1.5,
+        }
+    }
+
+    /// Fallback speed in meters per second.
+    pub fn default_speed(mut self, meters_per_second: f64) -> Self {
+        self.default_speed = meters_per_second;
+        self
+    }
+
+    /// Multiplier turning `VehicleData::speed` into meters per second (mph by default).
+    pub fn live_speed_factor(mut self, factor: f64) -> Self {
+        self.live_speed_factor = factor;
+        self
+    }
+
+    /// Learns average segment speeds from consecutive snapshots of the same vehicle.
+    pub fn learn(&mut self, history: &[VehicleSnapshot]) {
+        let mut by_vehicle: HashMap<(i64, &str), Vec<&VehicleSnapshot>> = HashMap::new();
+        for snapshot in history {
+            by_vehicle
+                .entry((snapshot.system_id, snapshot.vehicle.id.as_str()))
+                .or_default()
+                .push(snapshot);
+        }
+
+        for snapshots in by_vehicle.values_mut() {
+            snapshots.sort_by_key(|s| s.fetched_at);
+            for pair in snapshots.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let (Some(route_id), true) = (
+                    a.vehicle.route_id.as_ref(),
+                    a.vehicle.route_id == b.vehicle.route_id,
+                ) else {
+                    continue;
+                };
+                let seconds = (b.fetched_at - a.fetched_at).num_milliseconds() as f64 / 1000.0;
+                if !(1.0..=300.0).contains(&seconds) {
+                    continue;
+                }
+                let (Some(pa), Some(pb), Some(route)) = (
+                    self.matcher.progress(&a.vehicle),
+                    self.matcher.progress(&b.vehicle),
+                    self.matcher.route(route_id),
+                ) else {
+                    continue;
+                };
+                if pa.off_route || pb.off_route {
+                    continue;
+                }
+                let distance = pb.along_meters - pa.along_meters;
+                let speed = distance / seconds;
+                if distance <= 0.0 || speed > 30.0 {
+                    continue;
+                }
+                let segment = segment_at(route, (pa.along_meters + pb.along_meters) / 2.0);
+                let entry = self
+                    .segment_speeds
+                    .entry((route_id.clone(), segment))
+                    .or_insert((0.0, 0));
+                entry.0 += speed;
+                entry.1 += 1;
+            }
+        }
+    }
+
+    pub fn estimate(
+        &self,
+        stop_id: &str,
+        route_id: &str,
+        vehicles: &[VehicleData],
+    ) -> Vec<Prediction> {
+        let Some(route) = self.matcher.route(route_id) else {
+            return Vec::new();
+        };
+        let stop_alongs: Vec<f64> = route
+            .stops
+            .iter()
+            .filter(|(_, id)| id == stop_id)
+            .map(|(along, _)| *along)
+            .collect();
+        if stop_alongs.is_empty() {
+            return Vec::new();
+        }
+
+        let mut estimates: Vec<(f64, &VehicleData)> = vehicles
+            .iter()
+            .filter(|v| {
+                v.route_id.as_deref() == Some(route_id) && !v.out_of_service.unwrap_or(false)
+            })
+            .filter_map(|v| {
+                let progress = self.matcher.progress(v)?;
+                if progress.off_route {
+                    return None;
+                }
+                let target = stop_alongs
+                    .iter()
+                    .copied()
+                    .find(|a| *a >= progress.along_meters)
+                    .unwrap_or(stop_alongs[0] + route.length);
+                let live = v
+                    .speed
+                    .map(|s| s * self.live_speed_factor)
+                    .filter(|s| *s >= self.min_live_speed);
+                Some((
+                    self.travel_seconds(route_id, route, progress.along_meters, target, live),
+                    v,
+                ))
+            })
+            .collect();
+        estimates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        estimates
+            .into_iter()
+            .enumerate()
+            .map(|(order, (seconds, v))| Prediction {
+                source: EtaSource::Estimated,
+                seconds_away: Some(seconds),
+                data: ETAData {
+                    bus_name: v.name.clone().unwrap_or_else(|| v.id.clone()),
+                    eta: format_minutes(seconds),
+                    eta_note: Some("estimated".to_string()),
+                    order: Some(order as i64),
+                    route_id: route_id.to_string(),
+                    ..Default::default()
+                },
+            })
+            .collect()
+    }
+
+    // `to` may exceed the route length, meaning the vehicle wraps round a loop first.
+    fn travel_seconds(
+        &self,
+        route_id: &str,
+        route: &MatchedRoute,
+        from: f64,
+        to: f64,
+        live_speed: Option<f64>,
+    ) -> f64 {
+        let mut seconds = 0.0;
+        let mut at = from;
+        let mut first = true;
+        while at < to - f64::EPSILON {
+            let lap = (at / route.length).floor() * route.length;
+            let local = at - lap;
+            let segment = segment_at(route, local);
+            let segment_end = route
+                .stops
+                .get(segment)
+                .map(|(along, _)| *along)
+                .unwrap_or(route.length)
+                + lap;
+            let step_end = segment_end.min(to).max(at + 1.0).min(to);
+
+            let learned = self
+                .segment_speeds
+                .get(&(route_id.to_string(), segment))
+                .map(|(sum, n)| sum / *n as f64);
+            let speed = learned
+                .or(if first { live_speed } else { None })
+                .unwrap_or(self.default_speed)
+                .max(0.1);
+            seconds += (step_end - at) / speed;
+            at = step_end;
+            first = false;
+        }
+        seconds
+    }
+}
+
+// Segment `k` runs from stop `k - 1` to stop `k`; segment 0 precedes the first stop.
+fn segment_at(route: &MatchedRoute, along: f64) -> usize {
+    route.stops.partition_point(|(a, _)| *a <= along)
+}
+
+fn format_minutes(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as i64;
+    if minutes < 1 {
+        "<1 min".to_string()
+    } else {
+        format!("{} min", minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::types::{Coordinate, RouteShapeData, StopData};
+
+    // A 1 km square loop A → B → C → D → A; stops sit 0, 1000, 2000, 3000 and 4000 m along.
+    fn at(north: f64, east: f64) -> Coordinate {
+        Coordinate {
+            latitude: 41.0 + north / 111_195.0,
+            longitude: -87.0 + east / (111_195.0 * 41f64.to_radians().cos()),
+        }
+    }
+
+    fn stop(id: &str, corner: Coordinate, positions: &[f64]) -> StopData {
+        StopData {
+            id: id.to_string(),
+            latitude: Some(corner.latitude),
+            longitude: Some(corner.longitude),
+            routes_and_positions: [("1".to_string(), positions.to_vec())].into(),
+            ..Default::default()
+        }
+    }
+
+    fn estimator() -> EtaEstimator {
+        let stops = [
+            stop("A", at(0.0, 0.0), &[0.0, 4.0]),
+            stop("B", at(1000.0, 0.0), &[1.0]),
+            stop("C", at(1000.0, 1000.0), &[2.0]),
+            stop("D", at(0.0, 1000.0), &[3.0]),
+        ];
+        let shape = RouteShapeData {
+            route_id: "1".to_string(),
+            segments: vec![vec![
+                at(0.0, 0.0),
+                at(1000.0, 0.0),
+                at(1000.0, 1000.0),
+                at(0.0, 1000.0),
+                at(0.0, 0.0),
+            ]],
+            ..Default::default()
+        };
+        EtaEstimator::new(RouteMatcher::new(&[shape], &stops))
+            .default_speed(10.0)
+            .live_speed_factor(1.0)
+    }
+
+    fn bus(id: &str, position: Coordinate, speed: Option<f64>) -> VehicleData {
+        VehicleData {
+            id: id.to_string(),
+            route_id: Some("1".to_string()),
+            latitude: Some(position.latitude),
+            longitude: Some(position.longitude),
+            speed,
+            ..Default::default()
+        }
+    }
+
+    fn seconds(predictions: &[Prediction]) -> Vec<(String, f64)> {
+        predictions
+            .iter()
+            .map(|p| (p.data.bus_name.clone(), p.seconds_away.unwrap().round()))
+            .collect()
+    }
+
+    fn snapshot(seconds: i64, vehicle: VehicleData) -> VehicleSnapshot {
+        VehicleSnapshot {
+            fetched_at: DateTime::<Utc>::from_timestamp(1_741_000_000 + seconds, 0).unwrap(),
+            system_id: 1,
+            vehicle,
+        }
+    }
+
+    #[test]
+    fn estimates_from_default_and_live_speeds() {
+        let estimator = estimator();
+        let buses = [
+            bus("slow", at(500.0, 0.0), None),
+            // 5 m/s live speed, used only up to the next stop.
+            bus("live", at(500.0, 0.0), Some(5.0)),
+            // Too slow to trust, so the default speed applies.
+            bus("crawling", at(200.0, 0.0), Some(0.5)),
+        ];
+        let predictions = estimator.estimate("C", "1", &buses);
+        assert_eq!(
+            seconds(&predictions),
+            [
+                ("slow".to_string(), 150.0),
+                ("crawling".to_string(), 180.0),
+                ("live".to_string(), 200.0),
+            ]
+        );
+        assert_eq!(predictions[0].source, EtaSource::Estimated);
+        assert_eq!(predictions[2].data.eta, "3 min");
+        assert_eq!(predictions[2].data.order, Some(2));
+        assert!(estimator.estimate("Z", "1", &buses).is_empty());
+        assert!(estimator.estimate("C", "2", &buses).is_empty());
+    }
+
+    #[test]
+    fn wraps_round_the_loop() {
+        let estimator = estimator();
+        // 500 m before getting back to A.
+        let closing = [bus("closing", at(0.0, 500.0), None)];
+        assert_eq!(
+            seconds(&estimator.estimate("B", "1", &closing)),
+            [("closing".to_string(), 150.0)]
+        );
+        assert_eq!(
+            seconds(&estimator.estimate("A", "1", &closing)),
+            [("closing".to_string(), 50.0)]
+        );
+    }
+
+    #[test]
+    fn learned_segment_speeds_win() {
+        let mut estimator = estimator();
+        estimator.learn(&[
+            // 200 m in 10 s between B and C: 20 m/s.
+            snapshot(0, bus("a", at(1000.0, 200.0), None)),
+            snapshot(10, bus("a", at(1000.0, 400.0), None)),
+            // Ignored: too far apart in time, or moving backwards.
+            snapshot(1000, bus("a", at(1000.0, 500.0), None)),
+            snapshot(0, bus("b", at(0.0, 2.0), None)),
+            snapshot(10, bus("b", at(0.0, 800.0), None)),
+            snapshot(20, bus("b", at(0.0, 900.0), None)),
+        ]);
+        assert_eq!(estimator.segment_speeds.len(), 1);
+
+        let buses = [bus("c", at(1000.0, 0.0), Some(2.0))];
+        // B → C at the learned 20 m/s rather than the live 2 m/s, then C → D at 10 m/s.
+        assert_eq!(
+            seconds(&estimator.estimate("D", "1", &buses)),
+            [("c".to_string(), 150.0)]
+        );
+    }
+}
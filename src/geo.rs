@@ -0,0 +1,127 @@
+use crate::types::Coordinate;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoFilter {
+    BoundingBox {
+        min_latitude: f64,
+        min_longitude: f64,
+        max_latitude: f64,
+        max_longitude: f64,
+    },
+    Radius {
+        latitude: f64,
+        longitude: f64,
+        meters: f64,
+    },
+}
+
+impl GeoFilter {
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        match *self {
+            GeoFilter::BoundingBox {
+                min_latitude,
+                min_longitude,
+                max_latitude,
+                max_longitude,
+            } => {
+                (min_latitude..=max_latitude).contains(&latitude)
+                    && (min_longitude..=max_longitude).contains(&longitude)
+            }
+            GeoFilter::Radius {
+                latitude: center_lat,
+                longitude: center_lon,
+                meters,
+            } => haversine_meters(center_lat, center_lon, latitude, longitude) <= meters,
+        }
+    }
+}
+
+pub fn haversine_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+}
+
+impl Coordinate {
+    pub fn distance_meters(&self, other: &Coordinate) -> f64 {
+        haversine_meters(
+            self.latitude,
+            self.longitude,
+            other.latitude,
+            other.longitude,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PolylineProjection {
+    /// Closest point on the line.
+    pub point: Coordinate,
+    /// Index of the segment (`points[segment]..points[segment + 1]`) containing `point`.
+    pub segment: usize,
+    /// Distance from the start of the line to `point`, following the line.
+    pub along_meters: f64,
+    /// Distance from the original position to `point`.
+    pub offset_meters: f64,
+}
+
+/// Snaps `position` onto the closest segment of `points`. Uses a local flat-earth
+/// approximation, which is accurate enough at the scale of a transit route.
+pub fn project_onto_polyline(
+    points: &[Coordinate],
+    position: Coordinate,
+) -> Option<PolylineProjection> {
+    if points.len() < 2 {
+        return None;
+    }
+    let meters_per_deg_lat = EARTH_RADIUS_METERS.to_radians();
+    let meters_per_deg_lon = meters_per_deg_lat * position.latitude.to_radians().cos();
+    let to_xy = |c: &Coordinate| {
+        (
+            (c.longitude - position.longitude) * meters_per_deg_lon,
+            (c.latitude - position.latitude) * meters_per_deg_lat,
+        )
+    };
+
+    let mut best: Option<(f64, usize, f64)> = None;
+    for (idx, pair) in points.windows(2).enumerate() {
+        let (ax, ay) = to_xy(&pair[0]);
+        let (bx, by) = to_xy(&pair[1]);
+        let (dx, dy) = (bx - ax, by - ay);
+        let len_sq = dx * dx + dy * dy;
+        let t = if len_sq == 0.0 {
+            0.0
+        } else {
+            (-(ax * dx + ay * dy) / len_sq).clamp(0.0, 1.0)
+        };
+        let (px, py) = (ax + t * dx, ay + t * dy);
+        let offset = (px * px + py * py).sqrt();
+        if best.is_none_or(|(b, _, _)| offset < b) {
+            best = Some((offset, idx, t));
+        }
+    }
+
+    let (offset_meters, segment, t) = best?;
+    let (a, b) = (points[segment], points[segment + 1]);
+    let along_before: f64 = points[..=segment]
+        .windows(2)
+        .map(|w| w[0].distance_meters(&w[1]))
+        .sum();
+    Some(PolylineProjection {
+        point: Coordinate {
+            latitude: a.latitude + t * (b.latitude - a.latitude),
+            longitude: a.longitude + t * (b.longitude - a.longitude),
+        },
+        segment,
+        along_meters: along_before + t * a.distance_meters(&b),
+        offset_meters,
+    })
+}
+
+pub fn polyline_length(points: &[Coordinate]) -> f64 {
+    points.windows(2).map(|w| w[0].distance_meters(&w[1])).sum()
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::types::{Coordinate, RouteData, RouteShapeData, StopData, VehicleData};
+
+pub trait ToGeoJson {
+    fn to_geojson(&self) -> Value;
+}
+
+impl ToGeoJson for [StopData] {
+    fn to_geojson(&self) -> Value {
+        feature_collection(self.iter().filter_map(stop_feature).collect())
+    }
+}
+
+impl ToGeoJson for [VehicleData] {
+    fn to_geojson(&self) -> Value {
+        feature_collection(self.iter().filter_map(vehicle_feature).collect())
+    }
+}
+
+impl ToGeoJson for [RouteShapeData] {
+    fn to_geojson(&self) -> Value {
+        feature_collection(
+            self.iter()
+                .filter_map(|shape| route_feature(shape, None))
+                .collect(),
+        )
+    }
+}
+
+impl PassioGoClient {
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_system_geojson(&self, system_id: i64) -> Result<Value, ApiError> {
+        let (routes, (stops, shapes), buses) = tokio::try_join!(
+            self.get_routes(system_id),
+            self.get_stops_and_shapes(system_id),
+            self.get_buses(system_id),
+        )?;
+        Ok(GeoJsonBuilder::new()
+            .system_id(system_id)
+            .routes(&routes, &shapes)
+            .stops(&stops)
+            .vehicles(&buses)
+            .build())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct GeoJsonBuilder {
+    system_id: Option<i64>,
+    features: Vec<Value>,
+}
+
+impl GeoJsonBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn system_id(mut self, system_id: i64) -> Self {
+        self.system_id = Some(system_id);
+        self
+    }
+
+    pub fn stops(mut self, stops: &[StopData]) -> Self {
+        self.features.extend(stops.iter().filter_map(stop_feature));
+        self
+    }
+
+    /// Adds one LineString (or MultiLineString) per shape, styled with its route's
+    /// `group_color` when `routes` contains a matching `RouteData`.
+    pub fn routes(mut self, routes: &[RouteData], shapes: &[RouteShapeData]) -> Self {
+        let by_id: HashMap<&str, &RouteData> = routes.iter().map(|r| (r.id.as_str(), r)).collect();
+        self.features.extend(
+            shapes.iter().filter_map(|shape| {
+                route_feature(shape, by_id.get(shape.route_id.as_str()).copied())
+            }),
+        );
+        self
+    }
+
+    pub fn vehicles(mut self, vehicles: &[VehicleData]) -> Self {
+        self.features
+            .extend(vehicles.iter().filter_map(vehicle_feature));
+        self
+    }
+
+    pub fn build(self) -> Value {
+        let mut features = self.features;
+        if let Some(system_id) = self.system_id {
+            for feature in features.iter_mut() {
+                if let Some(props) = feature
+                    .get_mut("properties")
+                    .and_then(|p| p.as_object_mut())
+                {
+                    props.insert("system_id".to_string(), system_id.into());
+                }
+            }
+        }
+        feature_collection(features)
+    }
+}
+
+fn feature_collection(features: Vec<Value>) -> Value {
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+fn point(latitude: Option<f64>, longitude: Option<f64>) -> Option<Value> {
+    Some(json!({
+        "type": "Point",
+        "coordinates": [longitude?, latitude?],
+    }))
+}
+
+fn line(points: &[Coordinate]) -> Value {
+    points
+        .iter()
+        .map(|c| json!([c.longitude, c.latitude]))
+        .collect()
+}
+
+pub(crate) fn hex_color(color: &str) -> Option<String> {
+    let hex = color.trim().trim_start_matches('#');
+    (hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| format!("#{}", hex.to_ascii_lowercase()))
+}
+
+fn stop_feature(stop: &StopData) -> Option<Value> {
+    let mut routes: Vec<&String> = stop.routes_and_positions.keys().collect();
+    routes.sort();
+    Some(json!({
+        "type": "Feature",
+        "id": stop.id,
+        "geometry": point(stop.latitude, stop.longitude)?,
+        "properties": {
+            "kind": "stop",
+            "id": stop.id,
+            "name": stop.name,
+            "radius": stop.radius,
+            "routes": routes,
+        },
+    }))
+}
+
+fn vehicle_feature(vehicle: &VehicleData) -> Option<Value> {
+    let color = vehicle.color.as_deref().and_then(hex_color);
+    Some(json!({
+        "type": "Feature",
+        "id": vehicle.id,
+        "geometry": point(vehicle.latitude, vehicle.longitude)?,
+        "properties": {
+            "kind": "vehicle",
+            "id": vehicle.id,
+            "name": vehicle.name,
+            "route_id": vehicle.route_id,
+            "route_name": vehicle.route_name,
+            "heading": vehicle.calculated_course,
+            "speed": vehicle.speed,
+            "pax_load": vehicle.pax_load,
+            "out_of_service": vehicle.out_of_service,
+            "color": color,
+            "marker-color": color,
+        },
+    }))
+}
+
+fn route_feature(shape: &RouteShapeData, route: Option<&RouteData>) -> Option<Value> {
+    let geometry = match shape.segments.as_slice() {
+        [] => return None,
+        [only] => json!({ "type": "LineString", "coordinates": line(only) }),
+        many => json!({
+            "type": "MultiLineString",
+            "coordinates": many.iter().map(|s| line(s)).collect::<Vec<_>>(),
+        }),
+    };
+    let color = route
+        .and_then(|r| r.group_color.as_deref())
+        .and_then(hex_color);
+    Some(json!({
+        "type": "Feature",
+        "id": shape.route_id,
+        "geometry": geometry,
+        "properties": {
+            "kind": "route",
+            "id": shape.route_id,
+            "name": route.and_then(|r| r.name.clone()),
+            "short_name": route.and_then(|r| r.short_name.clone()),
+            "color": color,
+            "stroke": color,
+        },
+    }))
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::geojson::hex_color;
+use crate::helpers::xml_escape;
+use crate::kml::route_name;
+use crate::types::{RouteData, RouteShapeData, StopData};
+
+/// Writes stops as waypoints and route shapes as tracks. Route colors go into the
+/// topografix `gpx_style` extension, which most GIS tools understand.
+pub fn write_gpx<W: Write>(
+    mut w: W,
+    name: &str,
+    routes: &[RouteData],
+    shapes: &[RouteShapeData],
+    stops: &[StopData],
+) -> io::Result<()> {
+    let by_id: HashMap<&str, &RouteData> = routes.iter().map(|r| (r.id.as_str(), r)).collect();
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<gpx version="1.1" creator="passiogo_rs" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )?;
+    writeln!(w, "  <metadata>")?;
+    writeln!(w, "    <name>{}</name>", xml_escape(name))?;
+    writeln!(w, "  </metadata>")?;
+
+    for stop in stops {
+        let (Some(lat), Some(lon)) = (stop.latitude, stop.longitude) else {
+            continue;
+        };
+        let mut served: Vec<String> = stop
+            .routes_and_positions
+            .keys()
+            .map(|id| route_name(by_id.get(id.as_str()).copied(), id))
+            .collect();
+        served.sort();
+        writeln!(w, r#"  <wpt lat="{}" lon="{}">"#, lat, lon)?;
+        writeln!(
+            w,
+            "    <name>{}</name>",
+            xml_escape(stop.name.as_deref().unwrap_or(&stop.id))
+        )?;
+        if !served.is_empty() {
+            writeln!(
+                w,
+                "    <desc>Routes: {}</desc>",
+                xml_escape(&served.join(", "))
+            )?;
+        }
+        writeln!(w, "    <type>stop</type>")?;
+        writeln!(w, "  </wpt>")?;
+    }
+
+    for shape in shapes {
+        let route = by_id.get(shape.route_id.as_str()).copied();
+        writeln!(w, "  <trk>")?;
+        writeln!(
+            w,
+            "    <name>{}</name>",
+            xml_escape(&route_name(route, &shape.route_id))
+        )?;
+        writeln!(w, "    <type>route</type>")?;
+        if let Some(color) = route
+            .and_then(|r| r.group_color.as_deref())
+            .and_then(hex_color)
+        {
+            writeln!(w, "    <extensions>")?;
+            writeln!(
+                w,
+                r#"      <line xmlns="http://www.topografix.com/GPX/gpx_style/0/2">"#
+            )?;
+            writeln!(
+                w,
+                "        <color>{}</color>",
+                color.trim_start_matches('#')
+            )?;
+            writeln!(w, "      </line>")?;
+            writeln!(w, "    </extensions>")?;
+        }
+        for segment in &shape.segments {
+            writeln!(w, "    <trkseg>")?;
+            for point in segment {
+                writeln!(
+                    w,
+                    r#"      <trkpt lat="{}" lon="{}"/>"#,
+                    point.latitude, point.longitude
+                )?;
+            }
+            writeln!(w, "    </trkseg>")?;
+        }
+        writeln!(w, "  </trk>")?;
+    }
+
+    writeln!(w, "</gpx>")?;
+    Ok(())
+}
@@ -0,0 +1,367 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::geo::{polyline_length, project_onto_polyline};
+use crate::route_stops::stops_for_route;
+use crate::types::{Coordinate, StopData, VehicleData};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Headway {
+    pub route_id: String,
+    pub leader_id: String,
+    pub follower_id: String,
+    pub distance_meters: f64,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadwayEvent {
+    Bunching(Headway),
+    Gap(Headway),
+    Resolved {
+        route_id: String,
+        leader_id: String,
+        follower_id: String,
+    },
+}
+
+struct RouteLine {
+    points: Vec<Coordinate>,
+    is_loop: bool,
+}
+
+/// Measures the spacing between consecutive vehicles on each route.
+///
+/// Vehicles are placed along a line through the route's stops (in `routes_and_positions`
+/// order) and headway times are derived from `average_speed`, since the live `speed` drops
+/// to zero at every stop.
+pub struct HeadwayAnalyzer {
+    routes: HashMap<String, RouteLine>,
+    bunching_below: Duration,
+    gap_above: Duration,
+    average_speed: f64,
+    max_off_route: f64,
+    active: HashMap<(String, String, String), bool>,
+}
+
+impl HeadwayAnalyzer {
+    pub fn new(stops: &[StopData]) -> Self {
+        let route_ids: HashSet<&str> = stops
+            .iter()
+            .flat_map(|s| s.routes_and_positions.keys())
+            .map(String::as_str)
+            .collect();
+
+        let routes = route_ids
+            .into_iter()
+            .filter_map(|route_id| {
+                let ordered = stops_for_route(stops, route_id);
+                let points: Vec<Coordinate> = ordered
+                    .stops
+                    .iter()
+                    .filter_map(|s| {
+                        Some(Coordinate {
+                            latitude: s.stop.latitude?,
+                            longitude: s.stop.longitude?,
+                        })
+                    })
+                    .collect();
+                (points.len() >= 2).then(|| {
+                    (
+                        route_id.to_string(),
+                        RouteLine {
+                            points,
+                            is_loop: ordered.is_loop(),
+                        },
+                    )
+                })
+            })
+            .collect();
+
+        Self {
+            routes,
+            bunching_below: Duration::from_secs(120),
+            gap_above: Duration::from_secs(30 * 60),
+            average_speed: 4.5,
+            max_off_route: 250.0,
+            active: HashMap::new(),
+        }
+    }
+
+    pub fn bunching_below(mut self, headway: Duration) -> Self {
+        self.bunching_below = headway;
+        self
+    }
+
+    pub fn gap_above(mut self, headway: Duration) -> Self {
+        self.gap_above = headway;
+        self
+    }
+
+    /// Average in-service speed in meters per second, stops included.
+    pub fn average_speed(mut self, meters_per_second: f64) -> Self {
+        self.average_speed = meters_per_second;
+        self
+    }
+
+    /// Vehicles farther than this from their route's stop line are treated as deadheading.
+    pub fn max_off_route(mut self, meters: f64) -> Self {
+        self.max_off_route = meters;
+        self
+    }
+
+    pub fn headways(&self, vehicles: &[VehicleData]) -> Vec<Headway> {
+        let mut on_route: HashMap<&str, Vec<(f64, &str)>> = HashMap::new();
+        for vehicle in vehicles {
+            if vehicle.out_of_service.unwrap_or(false) {
+                continue;
+            }
+            let (Some(route_id), Some(latitude), Some(longitude)) = (
+                vehicle.route_id.as_deref(),
+                vehicle.latitude,
+                vehicle.longitude,
+            ) else {
+                continue;
+            };
+            let Some(line) = self.routes.get(route_id) else {
+                continue;
+            };
+            let Some(projection) = project_onto_polyline(
+                &line.points,
+                Coordinate {
+                    latitude,
+                    longitude,
+                },
+            ) else {
+                continue;
+            };
+            if projection.offset_meters <= self.max_off_route {
+                on_route
+                    .entry(route_id)
+                    .or_default()
+                    .push((projection.along_meters, vehicle.id.as_str()));
+            }
+        }
+
+        let mut headways = Vec::new();
+        for (route_id, mut placed) in on_route {
+            let line = &self.routes[route_id];
+            let length = polyline_length(&line.points);
+            placed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut pairs: Vec<(usize, usize)> = (1..placed.len()).map(|i| (i, i - 1)).collect();
+            if line.is_loop && placed.len() > 1 {
+                pairs.push((0, placed.len() - 1));
+            }
+            for (leader, follower) in pairs {
+                let mut distance = placed[leader].0 - placed[follower].0;
+                if distance < 0.0 {
+                    distance += length;
+                }
+                headways.push(Headway {
+                    route_id: route_id.to_string(),
+                    leader_id: placed[leader].1.to_string(),
+                    follower_id: placed[follower].1.to_string(),
+                    distance_meters: distance,
+                    time: Duration::from_secs_f64(distance / self.average_speed.max(0.1)),
+                });
+            }
+        }
+        headways.sort_by(|a, b| {
+            a.route_id
+                .cmp(&b.route_id)
+                .then(a.distance_meters.total_cmp(&b.distance_meters))
+        });
+        headways
+    }
+
+    /// Compares the current headways against the thresholds and reports pairs that have
+    /// just started bunching or gapping, and earlier reports that no longer apply.
+    pub fn analyze(&mut self, vehicles: &[VehicleData]) -> Vec<HeadwayEvent> {
+        let mut events = Vec::new();
+        let mut seen = HashSet::new();
+
+        for headway in self.headways(vehicles) {
+            let bunched = headway.time < self.bunching_below;
+            let gapped = headway.time > self.gap_above;
+            if !bunched && !gapped {
+                continue;
+            }
+            let key = (
+                headway.route_id.clone(),
+                headway.leader_id.clone(),
+                headway.follower_id.clone(),
+            );
+            seen.insert(key.clone());
+            if self.active.get(&key) == Some(&bunched) {
+                continue;
+            }
+            self.active.insert(key, bunched);
+            events.push(if bunched {
+                HeadwayEvent::Bunching(headway)
+            } else {
+                HeadwayEvent::Gap(headway)
+            });
+        }
+
+        let resolved: Vec<_> = self
+            .active
+            .keys()
+            .filter(|k| !seen.contains(*k))
+            .cloned()
+            .collect();
+        for key in resolved {
+            self.active.remove(&key);
+            let (route_id, leader_id, follower_id) = key;
+            events.push(HeadwayEvent::Resolved {
+                route_id,
+                leader_id,
+                follower_id,
+            });
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 1 km square loop A → B → C → D → A, 4 km around.
+    fn at(north: f64, east: f64) -> (f64, f64) {
+        let latitude = 41.0 + north / 111_195.0;
+        (
+            latitude,
+            -87.0 + east / (111_195.0 * 41f64.to_radians().cos()),
+        )
+    }
+
+    fn stop(id: &str, north: f64, east: f64, positions: &[f64]) -> StopData {
+        let (latitude, longitude) = at(north, east);
+        StopData {
+            id: id.to_string(),
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            routes_and_positions: [("1".to_string(), positions.to_vec())].into(),
+            ..Default::default()
+        }
+    }
+
+    fn square() -> Vec<StopData> {
+        vec![
+            stop("A", 0.0, 0.0, &[0.0, 4.0]),
+            stop("B", 1000.0, 0.0, &[1.0]),
+            stop("C", 1000.0, 1000.0, &[2.0]),
+            stop("D", 0.0, 1000.0, &[3.0]),
+        ]
+    }
+
+    fn bus(id: &str, north: f64, east: f64) -> VehicleData {
+        let (latitude, longitude) = at(north, east);
+        VehicleData {
+            id: id.to_string(),
+            route_id: Some("1".to_string()),
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..Default::default()
+        }
+    }
+
+    fn pairs(headways: &[Headway]) -> Vec<(&str, &str, f64)> {
+        headways
+            .iter()
+            .map(|h| {
+                (
+                    h.leader_id.as_str(),
+                    h.follower_id.as_str(),
+                    (h.distance_meters / 10.0).round() * 10.0,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_headways_wrap_past_the_start() {
+        let analyzer = HeadwayAnalyzer::new(&square()).average_speed(5.0);
+        // `lead` is 100 m past A; `trail` is 100 m before getting back to it.
+        let buses = [bus("lead", 100.0, 0.0), bus("trail", 0.0, 100.0)];
+        let headways = analyzer.headways(&buses);
+        assert_eq!(
+            pairs(&headways),
+            [("lead", "trail", 200.0), ("trail", "lead", 3800.0)]
+        );
+        assert_eq!(headways[0].time.as_secs(), 40);
+    }
+
+    #[test]
+    fn open_routes_do_not_wrap() {
+        let mut stops = square();
+        stops[0]
+            .routes_and_positions
+            .insert("1".to_string(), vec![0.0]);
+        let analyzer = HeadwayAnalyzer::new(&stops);
+        // On C → D and B → C: 1 km apart, and no pair measured back round through A.
+        let buses = [bus("lead", 100.0, 1000.0), bus("trail", 1000.0, 900.0)];
+        assert_eq!(
+            pairs(&analyzer.headways(&buses)),
+            [("lead", "trail", 1000.0)]
+        );
+    }
+
+    #[test]
+    fn skips_vehicles_off_route_or_out_of_service() {
+        let analyzer = HeadwayAnalyzer::new(&square()).max_off_route(100.0);
+        let parked = VehicleData {
+            out_of_service: Some(true),
+            ..bus("parked", 500.0, 0.0)
+        };
+        let buses = [bus("a", 200.0, 0.0), bus("depot", 500.0, 500.0), parked];
+        assert_eq!(analyzer.headways(&buses), []);
+    }
+
+    #[test]
+    fn bunching_is_reported_once_until_resolved() {
+        let mut analyzer = HeadwayAnalyzer::new(&square())
+            .average_speed(5.0)
+            .bunching_below(Duration::from_secs(120))
+            .gap_above(Duration::from_secs(700));
+
+        let bunched = [bus("lead", 100.0, 0.0), bus("trail", 0.0, 100.0)];
+        let events = analyzer.analyze(&bunched);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            HeadwayEvent::Bunching(h) if h.leader_id == "lead" && h.follower_id == "trail"
+        ));
+        assert!(matches!(
+            &events[1],
+            HeadwayEvent::Gap(h) if h.leader_id == "trail" && h.follower_id == "lead"
+        ));
+        assert_eq!(analyzer.analyze(&bunched), []);
+
+        // Spread evenly round the loop, 2 km (400 s) apart both ways.
+        let spread = [bus("lead", 1000.0, 900.0), bus("trail", 0.0, 100.0)];
+        let mut events = analyzer.analyze(&spread);
+        events.sort_by_key(|e| format!("{:?}", e));
+        assert_eq!(
+            events,
+            [
+                HeadwayEvent::Resolved {
+                    route_id: "1".to_string(),
+                    leader_id: "lead".to_string(),
+                    follower_id: "trail".to_string(),
+                },
+                HeadwayEvent::Resolved {
+                    route_id: "1".to_string(),
+                    leader_id: "trail".to_string(),
+                    follower_id: "lead".to_string(),
+                },
+            ]
+        );
+        assert_eq!(analyzer.analyze(&spread), []);
+        assert!(matches!(
+            analyzer.analyze(&bunched).as_slice(),
+            [HeadwayEvent::Bunching(_), HeadwayEvent::Gap(_)]
+        ));
+    }
+}
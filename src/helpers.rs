@@ -1,3 +1,5 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde_json::Value;
 
 pub fn to_string_opt(v: Option<&Value>) -> Option<String> {
@@ -61,3 +63,120 @@ pub fn to_f64(v: Option<&Value>) -> Option<f64> {
         }
     })
 }
+
+pub fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Seconds after midnight for clock times such as `7:05 AM`, `7pm`, `19:05` or `25:10:00`
+/// (GTFS-style times past midnight).
+pub fn parse_clock_time(s: &str) -> Option<u32> {
+    let s = s.trim().to_lowercase();
+    let (clock, meridiem) = if let Some(rest) = s.strip_suffix("am").or(s.strip_suffix("a.m.")) {
+        (rest.trim(), Some(false))
+    } else if let Some(rest) = s.strip_suffix("pm").or(s.strip_suffix("p.m.")) {
+        (rest.trim(), Some(true))
+    } else if s == "noon" {
+        return Some(12 * 3600);
+    } else if s == "midnight" {
+        return Some(0);
+    } else {
+        (s.as_str(), None)
+    };
+
+    let mut parts = clock.split(':').map(|p| p.trim().parse::<u32>());
+    let mut hours = parts.next()?.ok()?;
+    let minutes = parts.next().transpose().ok()?.unwrap_or(0);
+    let seconds = parts.next().transpose().ok()?.unwrap_or(0);
+    if parts.next().is_some() || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    match meridiem {
+        Some(pm) if (1..=12).contains(&hours) => hours = hours % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None => {}
+    }
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// `https://passiogo.com/mapGetData.php?getBuses=2&...` becomes `mapGetData.php?getBuses`.
+pub fn endpoint_label(url: &str) -> String {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let file = path.rsplit('/').next().unwrap_or(path);
+    match query.split('&').next().and_then(|p| p.split('=').next()) {
+        Some(key) if !key.is_empty() => format!("{}?{}", file, key),
+        _ => file.to_string(),
+    }
+}
+
+const LOCAL_TIMESTAMP_FORMATS: [&str; 6] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y%m%d %H:%M:%S%.f",
+    "%m/%d/%Y %H:%M:%S%.f",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %I:%M %p",
+];
+
+/// Reads RFC 3339 times, Unix seconds or milliseconds, and common offset-less date-times,
+/// which are taken as local times in `timezone`.
+pub fn parse_timestamp(s: &str, timezone: Tz) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(s) {
+        return Some(at.with_timezone(&Utc));
+    }
+    if let Ok(n) = s.parse::<i64>() {
+        return if n > 100_000_000_000 {
+            DateTime::from_timestamp_millis(n)
+        } else {
+            DateTime::from_timestamp(n, 0)
+        };
+    }
+    LOCAL_TIMESTAMP_FORMATS.iter().find_map(|format| {
+        let naive = NaiveDateTime::parse_from_str(s, format).ok()?;
+        timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|at| at.with_timezone(&Utc))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let expected = "2025-03-06T19:20:01Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(parse_timestamp("2025-03-06 14:20:01", tz), Some(expected));
+        assert_eq!(parse_timestamp("20250306 14:20:01", tz), Some(expected));
+        assert_eq!(parse_timestamp("03/06/2025 2:20:01 PM", tz), Some(expected));
+        assert_eq!(
+            parse_timestamp("2025-03-06T14:20:01-05:00", tz),
+            Some(expected)
+        );
+        assert_eq!(parse_timestamp("1741288801", tz), Some(expected));
+        assert_eq!(parse_timestamp("1741288801000", tz), Some(expected));
+        assert_eq!(parse_timestamp("2:20 PM", tz), None);
+    }
+}
@@ -0,0 +1,417 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+
+use crate::snapshot::VehicleSnapshot;
+use crate::types::VehicleData;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS vehicle_positions (
+    system_id INTEGER NOT NULL,
+    vehicle_id TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    route_id TEXT,
+    name TEXT,
+    type TEXT,
+    calculated_course REAL,
+    route_name TEXT,
+    color TEXT,
+    created TEXT,
+    latitude REAL,
+    longitude REAL,
+    speed REAL,
+    pax_load REAL,
+    out_of_service INTEGER,
+    more TEXT,
+    trip_id TEXT,
+    PRIMARY KEY (system_id, vehicle_id, fetched_at)
+);
+CREATE INDEX IF NOT EXISTS vehicle_positions_route
+    ON vehicle_positions (system_id, route_id, fetched_at);
+CREATE INDEX IF NOT EXISTS vehicle_positions_time
+    ON vehicle_positions (fetched_at);
+";
+
+const COLUMNS: &str = "system_id, vehicle_id, fetched_at, route_id, name, type, calculated_course, \
+    route_name, color, created, latitude, longitude, speed, pax_load, out_of_service, more, trip_id";
+
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    /// Rows older than this are deleted outright.
+    pub max_age: Option<Duration>,
+    /// Rows older than this are thinned to one per vehicle per `compact_interval`.
+    pub compact_after: Option<Duration>,
+    pub compact_interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    pub expired: usize,
+    pub compacted: usize,
+}
+
+/// Embedded SQLite store of vehicle positions, keyed by system, vehicle and fetch time.
+/// Re-ingesting the same snapshot replaces the earlier row.
+pub struct HistoryStore {
+    conn: Connection,
+}
+
+impl HistoryStore {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> rusqlite::Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn ingest(&mut self, snapshots: &[VehicleSnapshot]) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO vehicle_positions ({}) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+                COLUMNS
+            ))?;
+            for s in snapshots {
+                let v = &s.vehicle;
+                stmt.execute(params![
+                    s.system_id,
+                    v.id,
+                    s.fetched_at.timestamp_millis(),
+                    v.route_id,
+                    v.name,
+                    v.r#type,
+                    v.calculated_course,
+                    v.route_name,
+                    v.color,
+                    v.created,
+                    v.latitude,
+                    v.longitude,
+                    v.speed,
+                    v.pax_load,
+                    v.out_of_service,
+                    v.more,
+                    v.trip_id,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(snapshots.len())
+    }
+
+    /// Positions of one vehicle between `from` and `to` (inclusive), oldest first.
+    pub fn trajectory(
+        &self,
+        system_id: i64,
+        vehicle_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<VehicleSnapshot>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM vehicle_positions \
+             WHERE system_id = ?1 AND vehicle_id = ?2 AND fetched_at BETWEEN ?3 AND ?4 \
+             ORDER BY fetched_at",
+            COLUMNS
+        ))?;
+        stmt.query_map(
+            params![
+                system_id,
+                vehicle_id,
+                from.timestamp_millis(),
+                to.timestamp_millis()
+            ],
+            snapshot_from_row,
+        )?
+        .collect()
+    }
+
+    pub fn last_known(
+        &self,
+        system_id: i64,
+        vehicle_id: &str,
+    ) -> rusqlite::Result<Option<VehicleSnapshot>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM vehicle_positions \
+             WHERE system_id = ?1 AND vehicle_id = ?2 \
+             ORDER BY fetched_at DESC LIMIT 1",
+            COLUMNS
+        ))?;
+        stmt.query_row(params![system_id, vehicle_id], snapshot_from_row)
+            .optional()
+    }
+
+    /// The most recent position of every vehicle ever seen in a system.
+    pub fn last_known_all(&self, system_id: i64) -> rusqlite::Result<Vec<VehicleSnapshot>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM vehicle_positions p \
+             WHERE system_id = ?1 AND fetched_at = ( \
+                 SELECT MAX(fetched_at) FROM vehicle_positions \
+                 WHERE system_id = p.system_id AND vehicle_id = p.vehicle_id) \
+             ORDER BY vehicle_id",
+            COLUMNS
+        ))?;
+        stmt.query_map(params![system_id], snapshot_from_row)?
+            .collect()
+    }
+
+    /// All positions in a system between `from` and `to`, optionally limited to one route,
+    /// ordered by time and then vehicle.
+    pub fn positions_between(
+        &self,
+        system_id: i64,
+        route_id: Option<&str>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<VehicleSnapshot>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM vehicle_positions \
+             WHERE system_id = ?1 AND (?2 IS NULL OR route_id = ?2) \
+               AND fetched_at BETWEEN ?3 AND ?4 \
+             ORDER BY fetched_at, vehicle_id",
+            COLUMNS
+        ))?;
+        stmt.query_map(
+            params![
+                system_id,
+                route_id,
+                from.timestamp_millis(),
+                to.timestamp_millis()
+            ],
+            snapshot_from_row,
+        )?
+        .collect()
+    }
+
+    pub fn apply_retention(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> rusqlite::Result<RetentionReport> {
+        let mut report = RetentionReport::default();
+        let cutoff = |age: Duration| now.timestamp_millis() - age.as_millis() as i64;
+
+        if let Some(max_age) = policy.max_age {
+            report.expired = self.conn.execute(
+                "DELETE FROM vehicle_positions WHERE fetched_at < ?1",
+                params![cutoff(max_age)],
+            )?;
+        }
+
+        let interval = policy.compact_interval.as_millis() as i64;
+        if let Some(compact_after) = policy.compact_after
+            && interval > 0
+        {
+            report.compacted = self.conn.execute(
+                "DELETE FROM vehicle_positions \
+                 WHERE fetched_at < ?1 AND rowid NOT IN ( \
+                     SELECT MIN(rowid) FROM vehicle_positions WHERE fetched_at < ?1 \
+                     GROUP BY system_id, vehicle_id, fetched_at / ?2)",
+                params![cutoff(compact_after), interval],
+            )?;
+        }
+
+        Ok(report)
+    }
+
+    /// Reclaims the space freed by `apply_retention`.
+    pub fn vacuum(&self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("VACUUM")
+    }
+}
+
+fn snapshot_from_row(row: &Row<'_>) -> rusqlite::Result<VehicleSnapshot> {
+    let millis: i64 = row.get(2)?;
+    Ok(VehicleSnapshot {
+        system_id: row.get(0)?,
+        fetched_at: DateTime::from_timestamp_millis(millis).unwrap_or_default(),
+        vehicle: VehicleData {
+            id: row.get(1)?,
+            route_id: row.get(3)?,
+            name: row.get(4)?,
+            r#type: row.get(5)?,
+            calculated_course: row.get(6)?,
+            route_name: row.get(7)?,
+            color: row.get(8)?,
+            created: row.get(9)?,
+            latitude: row.get(10)?,
+            longitude: row.get(11)?,
+            speed: row.get(12)?,
+            pax_load: row.get(13)?,
+            out_of_service: row.get(14)?,
+            more: row.get(15)?,
+            trip_id: row.get(16)?,
+            extra: Default::default(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // On a whole minute, so compaction buckets line up with the offsets used below.
+    fn t(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_740_000_000 + seconds, 0).unwrap()
+    }
+
+    fn snap(system_id: i64, vehicle: &str, route: &str, seconds: i64) -> VehicleSnapshot {
+        VehicleSnapshot {
+            fetched_at: t(seconds),
+            system_id,
+            vehicle: VehicleData {
+                id: vehicle.to_string(),
+                route_id: Some(route.to_string()),
+                latitude: Some(41.0 + seconds as f64 * 1e-5),
+                longitude: Some(-87.0),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn key(s: &VehicleSnapshot) -> (&str, i64) {
+        (
+            s.vehicle.id.as_str(),
+            s.fetched_at.timestamp() - 1_740_000_000,
+        )
+    }
+
+    fn store(snapshots: &[VehicleSnapshot]) -> HistoryStore {
+        let mut store = HistoryStore::open_in_memory().unwrap();
+        store.ingest(snapshots).unwrap();
+        store
+    }
+
+    #[test]
+    fn trajectory_is_one_vehicle_in_time_order_with_inclusive_bounds() {
+        let store = store(&[
+            snap(1, "a", "R1", 30),
+            snap(1, "a", "R1", 10),
+            snap(1, "a", "R1", 20),
+            snap(1, "a", "R1", 40),
+            snap(1, "b", "R1", 20),
+            snap(2, "a", "R1", 20),
+        ]);
+        let rows = store.trajectory(1, "a", t(10), t(30)).unwrap();
+        let keys: Vec<_> = rows.iter().map(key).collect();
+        assert_eq!(keys, [("a", 10), ("a", 20), ("a", 30)]);
+        assert!(rows.iter().all(|s| s.system_id == 1));
+        assert_eq!(rows[1].vehicle.latitude, Some(41.0 + 20.0 * 1e-5));
+    }
+
+    #[test]
+    fn reingesting_replaces_the_earlier_row() {
+        let mut store = store(&[snap(1, "a", "R1", 10)]);
+        let mut moved = snap(1, "a", "R2", 10);
+        moved.vehicle.speed = Some(7.5);
+        store.ingest(&[moved]).unwrap();
+
+        let rows = store.trajectory(1, "a", t(0), t(100)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].vehicle.route_id.as_deref(), Some("R2"));
+        assert_eq!(rows[0].vehicle.speed, Some(7.5));
+    }
+
+    #[test]
+    fn positions_between_filters_by_route_when_given() {
+        let store = store(&[
+            snap(1, "b", "R1", 10),
+            snap(1, "a", "R1", 10),
+            snap(1, "c", "R2", 15),
+            snap(1, "a", "R1", 20),
+            snap(1, "a", "R1", 50),
+            snap(2, "z", "R1", 10),
+        ]);
+
+        let all = store.positions_between(1, None, t(10), t(20)).unwrap();
+        let keys: Vec<_> = all.iter().map(key).collect();
+        assert_eq!(keys, [("a", 10), ("b", 10), ("c", 15), ("a", 20)]);
+
+        let r2 = store
+            .positions_between(1, Some("R2"), t(0), t(100))
+            .unwrap();
+        let keys: Vec<_> = r2.iter().map(key).collect();
+        assert_eq!(keys, [("c", 15)]);
+    }
+
+    #[test]
+    fn last_known_all_is_the_latest_row_per_vehicle() {
+        let store = store(&[
+            snap(1, "b", "R1", 10),
+            snap(1, "a", "R1", 10),
+            snap(1, "a", "R1", 30),
+            snap(1, "b", "R1", 20),
+            snap(2, "a", "R1", 90),
+        ]);
+        let latest = store.last_known_all(1).unwrap();
+        let keys: Vec<_> = latest.iter().map(key).collect();
+        assert_eq!(keys, [("a", 30), ("b", 20)]);
+
+        let a = store.last_known(1, "a").unwrap().unwrap();
+        assert_eq!(key(&a), ("a", 30));
+        assert!(store.last_known(1, "missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn retention_expires_old_rows() {
+        let mut store = store(&[
+            snap(1, "a", "R1", 0),
+            snap(1, "a", "R1", 50),
+            snap(1, "a", "R1", 100),
+        ]);
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let report = store.apply_retention(&policy, t(120)).unwrap();
+        assert_eq!(
+            report,
+            RetentionReport {
+                expired: 2,
+                compacted: 0
+            }
+        );
+        let keys: Vec<_> = store
+            .trajectory(1, "a", t(0), t(200))
+            .unwrap()
+            .iter()
+            .map(|s| key(s).1)
+            .collect();
+        assert_eq!(keys, [100]);
+    }
+
+    #[test]
+    fn retention_thins_old_rows_to_one_per_vehicle_per_interval() {
+        // Two vehicles reporting every 10 s; anything older than t=100 keeps one row a minute.
+        let snapshots: Vec<_> = (0..15)
+            .flat_map(|i| [snap(1, "a", "R1", i * 10), snap(1, "b", "R1", i * 10)])
+            .collect();
+        let mut store = store(&snapshots);
+        let policy = RetentionPolicy {
+            compact_after: Some(Duration::from_secs(60)),
+            compact_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let report = store.apply_retention(&policy, t(160)).unwrap();
+        // Rows at 0..=90 are old: buckets [0, 60) and [60, 100) keep one each per vehicle.
+        assert_eq!(report.compacted, 2 * (10 - 2));
+        assert_eq!(report.expired, 0);
+
+        let a: Vec<_> = store
+            .trajectory(1, "a", t(0), t(200))
+            .unwrap()
+            .iter()
+            .map(|s| key(s).1)
+            .collect();
+        assert_eq!(a, [0, 60, 100, 110, 120, 130, 140]);
+        assert_eq!(store.trajectory(1, "b", t(0), t(200)).unwrap().len(), 7);
+    }
+}
@@ -0,0 +1,716 @@
+use crate::types::SystemAlertData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    PlainText,
+    Markdown,
+}
+
+impl SystemAlertData {
+    pub fn text(&self, max_chars: Option<usize>) -> Option<String> {
+        self.html
+            .as_deref()
+            .map(|html| render_alert_html(html, TextFormat::PlainText, max_chars))
+    }
+
+    pub fn markdown(&self, max_chars: Option<usize>) -> Option<String> {
+        self.html
+            .as_deref()
+            .map(|html| render_alert_html(html, TextFormat::Markdown, max_chars))
+    }
+}
+
+pub fn html_to_text(html: &str) -> String {
+    render_alert_html(html, TextFormat::PlainText, None)
+}
+
+pub fn html_to_markdown(html: &str) -> String {
+    render_alert_html(html, TextFormat::Markdown, None)
+}
+
+/// `max_chars` limits the rendered text, not counting Markdown syntax or line breaks.
+/// Longer text is cut on a word boundary with a trailing `…`, and any emphasis or link
+/// open at the cut is still closed.
+pub fn render_alert_html(html: &str, format: TextFormat, max_chars: Option<usize>) -> String {
+    let mut renderer = Renderer::new(format, None);
+    renderer.run(html);
+    match max_chars {
+        Some(0) => String::new(),
+        Some(max) if renderer.used > max => {
+            let mut renderer = Renderer::new(format, Some(max - 1));
+            renderer.run(html);
+            renderer.finish()
+        }
+        _ => renderer.finish(),
+    }
+}
+
+/// Cuts `text` to at most `max_chars` characters, ending on a word boundary
+/// with a trailing `…` when anything had to be dropped.
+pub fn truncate_with_ellipsis(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+
+    let keep: String = text.chars().take(max_chars - 1).collect();
+    let cut = match keep.rfind(char::is_whitespace) {
+        Some(idx) if idx > keep.len() / 2 => &keep[..idx],
+        _ => keep.as_str(),
+    };
+    format!("{}…", cut.trim_end())
+}
+
+enum ListKind {
+    Unordered,
+    Ordered(usize),
+}
+
+struct Renderer {
+    format: TextFormat,
+    out: String,
+    pending_space: bool,
+    lists: Vec<ListKind>,
+    links: Vec<(Option<String>, usize)>,
+    emphasis: Vec<(&'static str, usize)>,
+    in_pre: bool,
+    // Visible characters written so far, and how many fit before the text is cut.
+    used: usize,
+    limit: Option<usize>,
+    truncated: bool,
+    // Byte range of the latest uninterrupted run of text in `out`.
+    run_start: usize,
+    run_end: usize,
+}
+
+impl Renderer {
+    fn new(format: TextFormat, limit: Option<usize>) -> Self {
+        Self {
+            format,
+            out: String::new(),
+            pending_space: false,
+            lists: Vec::new(),
+            links: Vec::new(),
+            emphasis: Vec::new(),
+            in_pre: false,
+            used: 0,
+            limit,
+            truncated: false,
+            run_start: 0,
+            run_end: 0,
+        }
+    }
+
+    fn run(&mut self, html: &str) {
+        let mut rest = html;
+        while !rest.is_empty() && !self.truncated {
+            if let Some(after) = rest.strip_prefix("<!--") {
+                rest = match after.find("-->") {
+                    Some(end) => &after[end + 3..],
+                    None => "",
+                };
+                continue;
+            }
+
+            if rest.starts_with('<')
+                && let Some(end) = find_tag_end(rest)
+            {
+                let tag = &rest[1..end];
+                rest = &rest[end + 1..];
+                if let Some(skip_to) = self.tag(tag) {
+                    rest = skip_raw_text(rest, skip_to);
+                }
+                continue;
+            }
+
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            let next = rest[first..]
+                .find('<')
+                .map(|i| i + first)
+                .unwrap_or(rest.len());
+            let text = decode_entities(&rest[..next]);
+            self.text(&text);
+            rest = &rest[next..];
+        }
+    }
+
+    fn finish(mut self) -> String {
+        while let Some((marker, start)) = self.emphasis.pop() {
+            self.close_emphasis(marker, start);
+        }
+        while let Some((href, start)) = self.links.pop() {
+            self.close_link(href, start);
+        }
+        let mut lines: Vec<&str> = self.out.lines().map(|l| l.trim_end()).collect();
+        lines.dedup_by(|a, b| a.is_empty() && b.is_empty());
+        lines.join("\n").trim().to_string()
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_pre {
+            for c in text.chars() {
+                if !self.push_text(c, false) {
+                    return;
+                }
+            }
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.pending_space = true;
+                continue;
+            }
+            if self.pending_space
+                && !self.out.is_empty()
+                && !self.out.ends_with(char::is_whitespace)
+                && !self.push_text(' ', false)
+            {
+                return;
+            }
+            self.pending_space = false;
+            let escape = self.format == TextFormat::Markdown && needs_escape(c, &self.out);
+            if !self.push_text(c, escape) {
+                return;
+            }
+        }
+    }
+
+    fn push_text(&mut self, c: char, escape: bool) -> bool {
+        if !self.fits(1) {
+            return false;
+        }
+        if self.run_end != self.out.len() {
+            self.run_start = self.out.len();
+        }
+        if escape {
+            self.out.push('\\');
+        }
+        self.out.push(c);
+        self.run_end = self.out.len();
+        true
+    }
+
+    // Counts `n` more visible characters, cutting the text instead when they do not fit.
+    fn fits(&mut self, n: usize) -> bool {
+        if self.truncated {
+            return false;
+        }
+        if self.limit.is_some_and(|limit| self.used + n > limit) {
+            self.truncate();
+            return false;
+        }
+        self.used += n;
+        true
+    }
+
+    // Backs up to a word boundary within the current text run, so no markup is cut.
+    fn truncate(&mut self) {
+        self.truncated = true;
+        if self.run_end == self.out.len()
+            && let Some(idx) = self.out[self.run_start..].rfind(char::is_whitespace)
+            && self.run_start + idx > self.out.len() / 2
+        {
+            self.out.truncate(self.run_start + idx);
+        }
+        while self.out.ends_with(char::is_whitespace) {
+            self.out.pop();
+        }
+        self.out.push('…');
+    }
+
+    fn visible(&mut self, s: &str) {
+        if self.fits(s.chars().count()) {
+            self.inline(s);
+        }
+    }
+
+    fn inline(&mut self, s: &str) {
+        if self.pending_space && !self.out.is_empty() && !self.out.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+        self.out.push_str(s);
+    }
+
+    fn markdown(&mut self, s: &str) {
+        if self.format == TextFormat::Markdown {
+            self.inline(s);
+        }
+    }
+
+    fn line_break(&mut self, count: usize) {
+        self.pending_space = false;
+        while self.out.ends_with(' ') {
+            self.out.pop();
+        }
+        if self.out.is_empty() {
+            return;
+        }
+        let existing = self.out.len() - self.out.trim_end_matches('\n').len();
+        for _ in existing..count {
+            self.out.push('\n');
+        }
+    }
+
+    fn open_emphasis(&mut self, marker: &'static str) {
+        if self.format == TextFormat::Markdown {
+            self.inline("");
+            self.emphasis.push((marker, self.out.len()));
+        }
+    }
+
+    // Markdown only treats `**text**` as emphasis when the markers hug the text, so
+    // whitespace inside the tags is moved outside them.
+    fn close_emphasis(&mut self, marker: &'static str, start: usize) {
+        let start = start.min(self.out.len());
+        let inner = self.out.split_off(start);
+        let trimmed = inner.trim();
+        if inner.starts_with(char::is_whitespace) && !self.out.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+        if !trimmed.is_empty() {
+            self.out
+                .push_str(&format!("{}{}{}", marker, trimmed, marker));
+        }
+        if inner.ends_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+        let len = self.out.len();
+        for (_, link_start) in &mut self.links {
+            *link_start = (*link_start).min(len);
+        }
+    }
+
+    fn close_link(&mut self, href: Option<String>, start: usize) {
+        let start = start.min(self.out.len());
+        let Some(href) = href else {
+            return;
+        };
+        let label = self.out[start..].trim().to_string();
+        match self.format {
+            TextFormat::Markdown => {
+                let href = markdown_href(&href);
+                if label.is_empty() {
+                    if self.fits(href.chars().count()) {
+                        self.out.truncate(start);
+                        self.inline(&format!("<{}>", href));
+                    }
+                } else {
+                    self.out.truncate(start);
+                    self.out.push_str(&format!("[{}]({})", label, href));
+                }
+            }
+            TextFormat::PlainText => {
+                if label.is_empty() {
+                    self.visible(&href);
+                } else if label != href && self.fits(href.chars().count() + 3) {
+                    self.out.push_str(&format!(" ({})", href));
+                }
+            }
+        }
+    }
+
+    fn tag(&mut self, raw: &str) -> Option<&'static str> {
+        let raw = raw.trim();
+        let closing = raw.starts_with('/');
+        let body = raw.trim_start_matches('/').trim_end_matches('/');
+        let name_end = body.find(|c: char| c.is_whitespace()).unwrap_or(body.len());
+        let name = body[..name_end].to_ascii_lowercase();
+        let attrs = &body[name_end..];
+
+        match (name.as_str(), closing) {
+            ("script", false) => return Some("script"),
+            ("style", false) => return Some("style"),
+            ("head", false) => return Some("head"),
+            ("br", _) => self.line_break(1),
+            ("p", _) | ("blockquote", _) => self.line_break(2),
+            ("div", _) | ("tr", _) | ("table", _) => self.line_break(1),
+            ("hr", _) => {
+                self.line_break(2);
+                match self.format {
+                    TextFormat::Markdown => self.inline("---"),
+                    TextFormat::PlainText => self.visible("----"),
+                }
+                self.line_break(2);
+            }
+            ("td", true) | ("th", true) => self.pending_space = true,
+            ("pre", false) => {
+                self.line_break(2);
+                self.markdown("```");
+                self.line_break(1);
+                self.in_pre = true;
+            }
+            ("pre", true) => {
+                self.in_pre = false;
+                self.line_break(1);
+                self.markdown("```");
+                self.line_break(2);
+            }
+            (h, false) if is_heading(h) => {
+                self.line_break(2);
+                let level = h[1..].parse::<usize>().unwrap_or(1);
+                self.markdown(&format!("{} ", "#".repeat(level)));
+            }
+            (h, true) if is_heading(h) => self.line_break(2),
+            ("b", false) | ("strong", false) => self.open_emphasis("**"),
+            ("i", false) | ("em", false) => self.open_emphasis("_"),
+            ("b", true) | ("strong", true) | ("i", true) | ("em", true) => {
+                if let Some((marker, start)) = self.emphasis.pop() {
+                    self.close_emphasis(marker, start);
+                }
+            }
+            ("ul", false) => {
+                self.line_break(1);
+                self.lists.push(ListKind::Unordered);
+            }
+            ("ol", false) => {
+                self.line_break(1);
+                let start = attr(attrs, "start")
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .unwrap_or(1);
+                self.lists.push(ListKind::Ordered(start));
+            }
+            ("ul", true) | ("ol", true) => {
+                self.lists.pop();
+                self.line_break(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            ("li", false) => {
+                self.line_break(1);
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(ListKind::Ordered(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                let marker = format!("{}{}", "  ".repeat(depth), marker);
+                if self.fits(marker.trim_end().chars().count()) {
+                    self.out.push_str(&marker);
+                }
+            }
+            ("li", true) => self.line_break(1),
+            ("a", false) => {
+                let href = attr(attrs, "href").filter(|h| is_safe_href(h));
+                self.inline("");
+                self.links.push((href, self.out.len()));
+            }
+            ("a", true) => {
+                if let Some((href, start)) = self.links.pop() {
+                    self.close_link(href, start);
+                }
+            }
+            ("img", _) => {
+                if let Some(alt) = attr(attrs, "alt").filter(|a| !a.is_empty()) {
+                    self.text(&alt);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Characters Markdown would read as formatting. Headings (`#`) and list markers
+/// (`-`, `+`, `1.`) only count at the start of a line or list item.
+fn needs_escape(c: char, out: &str) -> bool {
+    match c {
+        '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' => true,
+        '#' | '-' | '+' => block_text(out).is_empty(),
+        '.' | ')' => {
+            let text = block_text(out);
+            !text.is_empty() && text.chars().all(|c| c.is_ascii_digit())
+        }
+        _ => false,
+    }
+}
+
+// The current line, after its indentation and any list marker we wrote.
+fn block_text(out: &str) -> &str {
+    let line = out.rsplit('\n').next().unwrap_or("").trim_start();
+    if let Some(rest) = line.strip_prefix("- ") {
+        return rest;
+    }
+    match line.split_once(". ") {
+        Some((n, rest)) if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => line,
+    }
+}
+
+/// Links are only kept for web and mail addresses, so `javascript:` and similar
+/// schemes never reach the output.
+fn is_safe_href(href: &str) -> bool {
+    let href = href.to_ascii_lowercase();
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| href.starts_with(scheme) && href.len() > scheme.len())
+}
+
+fn markdown_href(href: &str) -> String {
+    href.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+        .replace('<', "%3C")
+        .replace('>', "%3E")
+}
+
+fn is_heading(name: &str) -> bool {
+    name.len() == 2 && name.starts_with('h') && matches!(name.as_bytes()[1], b'1'..=b'6')
+}
+
+fn find_tag_end(s: &str) -> Option<usize> {
+    let first = s[1..].chars().next()?;
+    if !(first.is_ascii_alphabetic() || first == '/' || first == '!') {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn skip_raw_text<'a>(s: &'a str, tag: &str) -> &'a str {
+    let lower = s.to_ascii_lowercase();
+    let close = format!("</{}", tag);
+    match lower.find(&close) {
+        Some(start) => match s[start..].find('>') {
+            Some(end) => &s[start + end + 1..],
+            None => "",
+        },
+        None => "",
+    }
+}
+
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let mut rest = attrs;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+
+        let value = if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (value, remaining) = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let end = after[1..].find(q).map(|i| i + 1).unwrap_or(after.len());
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            rest = remaining;
+            Some(value)
+        } else {
+            None
+        };
+
+        if key.eq_ignore_ascii_case(name) {
+            return value.map(|v| decode_entities(v).trim().to_string());
+        }
+    }
+}
+
+pub fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(num) = name.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => num.parse::<u32>().ok(),
+        };
+        return code.and_then(char::from_u32);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "times" => '×',
+        "larr" => '←',
+        "rarr" => '→',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_runs_starting_with_multibyte_chars() {
+        assert_eq!(html_to_text("<p>• Route 5 detour</p>"), "• Route 5 detour");
+        assert_eq!(html_to_text("<p>été</p>"), "été");
+        assert_eq!(html_to_markdown("<p>été</p><p>日本</p>"), "été\n\n日本");
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            html_to_text("Stops &amp; shelters &ndash; closed&nbsp;today &#8226; &#x2192; &bogus;"),
+            "Stops & shelters – closed today • → &bogus;"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let html = "<p>Detours:</p><ul><li>Route 1</li><li>Route 2<ol start=\"3\"><li>Main St</li></ol></li></ul>";
+        assert_eq!(
+            html_to_text(html),
+            "Detours:\n\n- Route 1\n- Route 2\n  3. Main St"
+        );
+    }
+
+    #[test]
+    fn links() {
+        let html = "See <a href=\"https://example.edu/detour\">the map</a> or <a href=\"https://example.edu\"></a>.";
+        assert_eq!(
+            html_to_text(html),
+            "See the map (https://example.edu/detour) or https://example.edu."
+        );
+        assert_eq!(
+            html_to_markdown(html),
+            "See [the map](https://example.edu/detour) or <https://example.edu>."
+        );
+    }
+
+    #[test]
+    fn emphasis_keeps_markers_next_to_text() {
+        assert_eq!(
+            html_to_markdown("Buses <b> now </b>run"),
+            "Buses **now** run"
+        );
+        assert_eq!(
+            html_to_markdown("<strong>Closed</strong> <em>today</em>"),
+            "**Closed** _today_"
+        );
+        assert_eq!(html_to_markdown("a<b> </b>b"), "a b");
+        assert_eq!(html_to_text("Buses <b> now </b>run"), "Buses now run");
+    }
+
+    #[test]
+    fn markdown_escapes_metacharacters() {
+        assert_eq!(
+            html_to_markdown("Use stop_id *12* [north] &lt;gate&gt;"),
+            "Use stop\\_id \\*12\\* \\[north\\] \\<gate\\>"
+        );
+        assert_eq!(html_to_markdown("<p>#5 bus</p>"), "\\#5 bus");
+        assert_eq!(html_to_text("stop_id *12*"), "stop_id *12*");
+    }
+
+    #[test]
+    fn markdown_escapes_list_markers_at_line_start() {
+        assert_eq!(
+            html_to_markdown("<p>1. Board at gate</p><p>- Exit</p><p>+ More</p>"),
+            "1\\. Board at gate\n\n\\- Exit\n\n\\+ More"
+        );
+        assert_eq!(
+            html_to_markdown("<ul><li>- Route 5</li><li>2) Route 6</li></ul>"),
+            "- \\- Route 5\n- 2\\) Route 6"
+        );
+        assert_eq!(
+            html_to_markdown("Route 1. Stop 2 - 3 + 4"),
+            "Route 1. Stop 2 - 3 + 4"
+        );
+    }
+
+    #[test]
+    fn links_only_keep_web_and_mail_addresses() {
+        let html = "<a href=\"javascript:alert(1)\">click</a> <a href=\" JavaScript:x\">here</a> \
+                    <a href=\"data:text/html,x\">data</a> <a href=\"mailto:transit@example.edu\">mail</a>";
+        assert_eq!(
+            html_to_markdown(html),
+            "click here data [mail](mailto:transit@example.edu)"
+        );
+        assert_eq!(
+            html_to_text(html),
+            "click here data mail (mailto:transit@example.edu)"
+        );
+        assert_eq!(
+            html_to_markdown("<a href=\"https://example.edu/a (b)\">map</a>"),
+            "[map](https://example.edu/a%20%28b%29)"
+        );
+    }
+
+    #[test]
+    fn truncation_closes_markup() {
+        let html = "<b>Important notice about</b> detours on <a href=\"https://example.edu\">Route 5 today</a>";
+        let bold = render_alert_html(html, TextFormat::Markdown, Some(20));
+        assert_eq!(bold, "**Important notice…**");
+        assert_eq!(
+            render_alert_html(html, TextFormat::PlainText, Some(20)),
+            "Important notice…"
+        );
+
+        let link = render_alert_html(html, TextFormat::Markdown, Some(45));
+        assert_eq!(
+            link,
+            "**Important notice about** detours on [Route 5…](https://example.edu)"
+        );
+        assert_eq!(
+            render_alert_html(html, TextFormat::PlainText, Some(45)),
+            "Important notice about detours on Route 5…"
+        );
+
+        // Text that fits is left alone, whatever the markup adds.
+        assert_eq!(
+            render_alert_html("<b>Short</b>", TextFormat::Markdown, Some(5)),
+            "**Short**"
+        );
+        assert_eq!(render_alert_html(html, TextFormat::Markdown, Some(0)), "");
+    }
+}
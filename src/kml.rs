@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::geojson::hex_color;
+use crate::helpers::xml_escape;
+use crate::types::{Coordinate, RouteData, RouteShapeData, StopData};
+
+pub fn write_kml<W: Write>(
+    mut w: W,
+    name: &str,
+    routes: &[RouteData],
+    shapes: &[RouteShapeData],
+    stops: &[StopData],
+) -> io::Result<()> {
+    let by_id: HashMap<&str, &RouteData> = routes.iter().map(|r| (r.id.as_str(), r)).collect();
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(w, "<Document>")?;
+    writeln!(w, "  <name>{}</name>", xml_escape(name))?;
+
+    for shape in shapes {
+        let color = by_id
+            .get(shape.route_id.as_str())
+            .and_then(|r| r.group_color.as_deref())
+            .and_then(hex_color)
+            .unwrap_or_else(|| "#3366cc".to_string());
+        writeln!(w, r#"  <Style id="route-{}">"#, xml_escape(&shape.route_id))?;
+        writeln!(w, "    <LineStyle>")?;
+        writeln!(w, "      <color>{}</color>", kml_color(&color))?;
+        writeln!(w, "      <width>4</width>")?;
+        writeln!(w, "    </LineStyle>")?;
+        writeln!(w, "  </Style>")?;
+    }
+
+    writeln!(w, "  <Folder>")?;
+    writeln!(w, "    <name>Routes</name>")?;
+    for shape in shapes {
+        let route = by_id.get(shape.route_id.as_str()).copied();
+        writeln!(w, "    <Placemark>")?;
+        writeln!(
+            w,
+            "      <name>{}</name>",
+            xml_escape(&route_name(route, &shape.route_id))
+        )?;
+        writeln!(
+            w,
+            "      <styleUrl>#route-{}</styleUrl>",
+            xml_escape(&shape.route_id)
+        )?;
+        if shape.segments.len() > 1 {
+            writeln!(w, "      <MultiGeometry>")?;
+        }
+        for segment in &shape.segments {
+            writeln!(w, "      <LineString>")?;
+            writeln!(w, "        <tessellate>1</tessellate>")?;
+            writeln!(
+                w,
+                "        <coordinates>{}</coordinates>",
+                kml_coordinates(segment)
+            )?;
+            writeln!(w, "      </LineString>")?;
+        }
+        if shape.segments.len() > 1 {
+            writeln!(w, "      </MultiGeometry>")?;
+        }
+        writeln!(w, "    </Placemark>")?;
+    }
+    writeln!(w, "  </Folder>")?;
+
+    writeln!(w, "  <Folder>")?;
+    writeln!(w, "    <name>Stops</name>")?;
+    for stop in stops {
+        let (Some(lat), Some(lon)) = (stop.latitude, stop.longitude) else {
+            continue;
+        };
+        let mut served: Vec<String> = stop
+            .routes_and_positions
+            .keys()
+            .map(|id| route_name(by_id.get(id.as_str()).copied(), id))
+            .collect();
+        served.sort();
+        writeln!(w, "    <Placemark>")?;
+        writeln!(
+            w,
+            "      <name>{}</name>",
+            xml_escape(stop.name.as_deref().unwrap_or(&stop.id))
+        )?;
+        if !served.is_empty() {
+            writeln!(
+                w,
+                "      <description>Routes: {}</description>",
+                xml_escape(&served.join(", "))
+            )?;
+        }
+        writeln!(w, "      <Point>")?;
+        writeln!(w, "        <coordinates>{},{},0</coordinates>", lon, lat)?;
+        writeln!(w, "      </Point>")?;
+        writeln!(w, "    </Placemark>")?;
+    }
+    writeln!(w, "  </Folder>")?;
+
+    writeln!(w, "</Document>")?;
+    writeln!(w, "</kml>")?;
+    Ok(())
+}
+
+pub(crate) fn route_name(route: Option<&RouteData>, route_id: &str) -> String {
+    route
+        .and_then(|r| r.name.clone().or_else(|| r.short_name.clone()))
+        .unwrap_or_else(|| route_id.to_string())
+}
+
+// KML colors are aabbggrr rather than #rrggbb.
+fn kml_color(hex: &str) -> String {
+    let hex = hex.trim_start_matches('#');
+    format!("ff{}{}{}", &hex[4..6], &hex[2..4], &hex[0..2])
+}
+
+fn kml_coordinates(points: &[Coordinate]) -> String {
+    points
+        .iter()
+        .map(|c| format!("{},{},0", c.longitude, c.latitude))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
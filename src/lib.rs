@@ -1,18 +1,88 @@
-use serde_json::Value;
-
-use crate::helpers::{to_bool, to_f64, to_i64, to_string_opt};
-
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
+
+use crate::diagnostics::ParseWarnings;
+use crate::helpers::{endpoint_label, to_f64, to_string_opt};
+
+#[cfg(feature = "parquet")]
+mod archive;
+mod arrivals;
+#[cfg(feature = "arrow")]
+mod columnar;
+mod diagnostics;
+mod error;
+mod estimate;
+mod geo;
+mod geojson;
+mod gpx;
+mod headway;
 mod helpers;
+#[cfg(feature = "history")]
+mod history;
+mod html;
+mod kml;
+mod matching;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod multi;
+mod occupancy;
+mod planner;
+mod route_stops;
+mod schedule;
+mod search;
+mod service;
+mod snapshot;
+mod system;
+mod transfers;
 mod types;
 
+#[cfg(feature = "parquet")]
+pub use archive::ParquetArchiveWriter;
+pub use arrivals::{ArrivalDetector, StopEvent};
+#[cfg(feature = "arrow")]
+pub use columnar::{
+    alert_schema, alerts_to_record_batch, eta_schema, etas_to_record_batch, vehicle_schema,
+    vehicles_to_record_batch,
+};
+pub use diagnostics::{DecodeError, ParseMode, ParseWarning, Parsed};
+pub use error::ApiError;
+pub use estimate::{EtaEstimator, EtaSource, Prediction};
+pub use geo::{
+    GeoFilter, PolylineProjection, haversine_meters, polyline_length, project_onto_polyline,
+};
+pub use geojson::{GeoJsonBuilder, ToGeoJson};
+pub use gpx::write_gpx;
+pub use headway::{Headway, HeadwayAnalyzer, HeadwayEvent};
+#[cfg(feature = "history")]
+pub use history::{HistoryStore, RetentionPolicy, RetentionReport};
+pub use html::{
+    TextFormat, decode_entities, html_to_markdown, html_to_text, render_alert_html,
+    truncate_with_ellipsis,
+};
+pub use kml::write_kml;
+pub use matching::{RouteMatcher, VehicleProgress};
+#[cfg(feature = "metrics")]
+pub use metrics::{MetricsRegistry, serve_metrics};
+pub use multi::{MultiSystemClient, MultiSystemResult, SystemFetchError, Tagged};
+pub use occupancy::{Occupancy, OccupancyModel, RouteOccupancy};
+pub use planner::{Itinerary, Leg, TripPlanner};
+pub use route_stops::{RouteStop, RouteStops, stops_for_route};
+pub use schedule::ScheduleComparison;
+pub use search::{SystemMatch, rank_systems};
+pub use service::{ServiceHours, ServiceWindow};
+pub use snapshot::{SnapshotFormat, SnapshotWriter, VehicleSnapshot};
+pub use system::SystemSnapshot;
+pub use transfers::{Transfer, TransferGraph, TransferGraphBuilder};
 pub use types::{
-    ETAData, RouteData, StopData, SystemAlertData, TransportationSystemData, VehicleData,
+    Coordinate, ETAData, RouteData, RouteShapeData, StopData, SystemAlertData,
+    TransportationSystemData, VehicleData,
 };
 
 #[derive(Default, Debug, Clone)]
 pub struct PassioGoClient {
     base_url: String,
     client: reqwest::Client,
+    keep_extra: bool,
 }
 
 impl PassioGoClient {
@@ -20,23 +90,91 @@ impl PassioGoClient {
         Self {
             base_url: "https://passiogo.com".to_string(),
             client: reqwest::Client::new(),
+            keep_extra: false,
         }
     }
 
-    async fn send_api_request(
-        &self,
-        url: &str,
-        body: Option<Value>,
-    ) -> Result<Value, reqwest::Error> {
-        let resp = if let Some(json) = body {
-            self.client.post(url).json(&json).send().await?
-        } else {
-            self.client.get(url).send().await?
-        };
-        resp.json::<Value>().await
+    /// Keeps payload fields this crate does not map in each record's `extra`, so new
+    /// Passio fields can be read before they are modelled. Off by default to save memory.
+    pub fn keep_extra_fields(mut self) -> Self {
+        self.keep_extra = true;
+        self
+    }
+
+    fn extra_fields(&self, value: &Value, known: &[&str]) -> Map<String, Value> {
+        if !self.keep_extra {
+            return Map::new();
+        }
+        value
+            .as_object()
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter(|(key, _)| !known.contains(&key.as_str()))
+                    .map(|(key, v)| (key.clone(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[tracing::instrument(
+        level = "debug",
+        skip(self, body),
+        fields(
+            endpoint = %endpoint_label(url),
+            status = tracing::field::Empty,
+            bytes = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        )
+    )]
+    async fn send_api_request(&self, url: &str, body: Option<Value>) -> Result<Value, ApiError> {
+        let started = std::time::Instant::now();
+
+        let result = async {
+            let resp = if let Some(json) = body {
+                self.client.post(url).json(&json).send().await?
+            } else {
+                self.client.get(url).send().await?
+            };
+            let span = tracing::Span::current();
+            let status = resp.status();
+            span.record("status", status.as_u16());
+            if !status.is_success() {
+                warn!(status = status.as_u16(), "unexpected HTTP status");
+            }
+            let body = resp.bytes().await?;
+            span.record("bytes", body.len());
+            Ok(serde_json::from_slice::<Value>(&body)?)
+        }
+        .await;
+
+        let elapsed = started.elapsed();
+        tracing::Span::current().record("elapsed_ms", elapsed.as_millis() as u64);
+        match &result {
+            Ok(_) => debug!("request completed"),
+            Err(e) => warn!(error = %e, "request failed"),
+        }
+
+        #[cfg(feature = "metrics")]
+        MetricsRegistry::global().record_request(url, elapsed, result.is_ok());
+
+        result
+    }
+
+    pub async fn get_systems(&self) -> Result<Vec<TransportationSystemData>, ApiError> {
+        self.fetch_systems(&mut ParseWarnings::default()).await
     }
 
-    pub async fn get_systems(&self) -> Result<Vec<TransportationSystemData>, reqwest::Error> {
+    #[tracing::instrument(
+        name = "get_systems",
+        level = "debug",
+        skip(self, warnings),
+        fields(count = tracing::field::Empty)
+    )]
+    async fn fetch_systems(
+        &self,
+        warnings: &mut ParseWarnings,
+    ) -> Result<Vec<TransportationSystemData>, ApiError> {
         let url = format!(
             "{}/mapGetData.php?getSystems=2&sortMode=1&credentials=1",
             self.base_url
@@ -48,35 +186,54 @@ impl PassioGoClient {
             .and_then(|v| v.as_array())
             .cloned()
             .unwrap_or_default();
+        if list.is_empty() {
+            warn!("response has no systems under `all`");
+        }
         for sys in list {
-            let id = sys
-                .get("id")
-                .and_then(|v| v.as_str())
-                .and_then(|v| v.parse::<i64>().ok())
-                .or_else(|| sys.get("id").and_then(|v| v.as_i64()))
-                .unwrap_or(0);
+            let mut r = warnings.record_by(&sys, "id");
+            let id = r.required_i64("id").unwrap_or_else(|| {
+                warn!(raw = ?sys.get("id"), "system id is not an integer, using 0");
+                0
+            });
 
             systems.push(TransportationSystemData {
                 id,
-                name: to_string_opt(sys.get("fullname")),
-                username: to_string_opt(sys.get("username")),
-                go_agency_name: to_string_opt(sys.get("goAgencyName")),
-                email: to_string_opt(sys.get("email")),
-                go_test_mode: to_bool(sys.get("goTestMode")),
-                name2: to_bool(sys.get("name2")),
-                homepage: to_string_opt(sys.get("homepage")),
-                logo: to_bool(sys.get("logo")),
-                go_route_planner_enabled: to_bool(sys.get("goRoutePlannerEnabled")),
-                go_color: to_string_opt(sys.get("goColor")),
-                go_support_email: to_string_opt(sys.get("goSupportEmail")),
-                go_shared_code: to_i64(sys.get("goSharedCode")),
-                go_authentication_type: to_bool(sys.get("goAuthenticationType")),
+                name: r.string("fullname"),
+                username: r.string("username"),
+                go_agency_name: r.string("goAgencyName"),
+                email: r.string("email"),
+                go_test_mode: r.bool("goTestMode"),
+                name2: r.bool("name2"),
+                homepage: r.string("homepage"),
+                logo: r.bool("logo"),
+                go_route_planner_enabled: r.bool("goRoutePlannerEnabled"),
+                go_color: r.string("goColor"),
+                go_support_email: r.string("goSupportEmail"),
+                go_shared_code: r.i64("goSharedCode"),
+                go_authentication_type: r.bool("goAuthenticationType"),
+                extra: self.extra_fields(&sys, SYSTEM_FIELDS),
             });
         }
+        tracing::Span::current().record("count", systems.len());
         Ok(systems)
     }
 
-    pub async fn get_alerts(&self, system_id: i64) -> Result<Vec<SystemAlertData>, reqwest::Error> {
+    pub async fn get_alerts(&self, system_id: i64) -> Result<Vec<SystemAlertData>, ApiError> {
+        self.fetch_alerts(system_id, &mut ParseWarnings::default())
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "get_alerts",
+        level = "debug",
+        skip(self, warnings),
+        fields(count = tracing::field::Empty)
+    )]
+    async fn fetch_alerts(
+        &self,
+        system_id: i64,
+        warnings: &mut ParseWarnings,
+    ) -> Result<Vec<SystemAlertData>, ApiError> {
         let url = format!("{}/goServices.php?getAlertMessages=1", self.base_url);
         let body = serde_json::json!({
             "systemSelected0": system_id.to_string(),
@@ -97,49 +254,62 @@ impl PassioGoClient {
         let mut msgs = Vec::new();
 
         for m in list {
-            let id = m
-                .get("id")
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| "".to_string())
-                .trim_matches('"')
-                .to_string();
+            let mut r = warnings.record_by(&m, "id");
+            let id = r.record_id().to_string();
 
             msgs.push(SystemAlertData {
-                id,
-                system_id: to_i64(m.get("userId")),
-                route_id: to_string_opt(m.get("routeId")),
-                name: to_string_opt(m.get("name")),
-                html: to_string_opt(m.get("html")),
-                archive: to_bool(m.get("archive")),
-                important: to_bool(m.get("important")),
-                date_time_created: to_string_opt(m.get("created")),
-                date_time_from: to_string_opt(m.get("from")),
-                date_time_to: to_string_opt(m.get("to")),
-                as_push: to_bool(m.get("asPush")),
-                gtfs: to_bool(m.get("gtfs")),
-                gtfs_alert_cause_id: to_i64(m.get("gtfsAlertCauseId")),
-                gtfs_alert_effect_id: to_i64(m.get("gtfsAlertEffectId")),
-                gtfs_alert_url: to_string_opt(m.get("gtfsAlertUrl")),
-                gtfs_alert_header_text: to_string_opt(m.get("gtfsAlertHeaderText")),
-                gtfs_alert_description_text: to_string_opt(m.get("gtfsAlertDescriptionText")),
-                route_group_id: to_i64(m.get("routeGroupId")),
-                created_utc: to_string_opt(m.get("createdUtc")),
-                author_id: to_i64(m.get("authorId")),
-                author: to_string_opt(m.get("author")),
-                updated: to_string_opt(m.get("updated")),
-                update_author_id: to_i64(m.get("updateAuthorId")),
-                update_author: to_string_opt(m.get("updateAuthor")),
-                created_f: to_string_opt(m.get("createdF")),
-                from_f: to_string_opt(m.get("fromF")),
-                from_ok: to_bool(m.get("fromOk")),
-                to_ok: to_bool(m.get("toOk")),
+                id: id.clone(),
+                system_id: r.i64("userId"),
+                route_id: r.string("routeId"),
+                name: r.string("name"),
+                html: r.string("html"),
+                archive: r.bool("archive"),
+                important: r.bool("important"),
+                date_time_created: r.string("created"),
+                date_time_from: r.string("from"),
+                date_time_to: r.string("to"),
+                as_push: r.bool("asPush"),
+                gtfs: r.bool("gtfs"),
+                gtfs_alert_cause_id: r.i64("gtfsAlertCauseId"),
+                gtfs_alert_effect_id: r.i64("gtfsAlertEffectId"),
+                gtfs_alert_url: r.string("gtfsAlertUrl"),
+                gtfs_alert_header_text: r.string("gtfsAlertHeaderText"),
+                gtfs_alert_description_text: r.string("gtfsAlertDescriptionText"),
+                route_group_id: r.i64("routeGroupId"),
+                created_utc: r.string("createdUtc"),
+                author_id: r.i64("authorId"),
+                author: r.string("author"),
+                updated: r.string("updated"),
+                update_author_id: r.i64("updateAuthorId"),
+                update_author: r.string("updateAuthor"),
+                created_f: r.string("createdF"),
+                from_f: r.string("fromF"),
+                from_ok: r.bool("fromOk"),
+                to_ok: r.bool("toOk"),
+                extra: self.extra_fields(&m, ALERT_FIELDS),
             });
         }
 
+        tracing::Span::current().record("count", msgs.len());
         Ok(msgs)
     }
 
-    pub async fn get_routes(&self, system_id: i64) -> Result<Vec<RouteData>, reqwest::Error> {
+    pub async fn get_routes(&self, system_id: i64) -> Result<Vec<RouteData>, ApiError> {
+        self.fetch_routes(system_id, &mut ParseWarnings::default())
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "get_routes",
+        level = "debug",
+        skip(self, warnings),
+        fields(count = tracing::field::Empty)
+    )]
+    async fn fetch_routes(
+        &self,
+        system_id: i64,
+        warnings: &mut ParseWarnings,
+    ) -> Result<Vec<RouteData>, ApiError> {
         let url = format!("{}/mapGetData.php?getRoutes=1", self.base_url);
         let body = serde_json::json!({
             "systemSelected0": system_id.to_string(),
@@ -158,42 +328,55 @@ impl PassioGoClient {
 
         let mut routes = Vec::new();
 
-        for r in list {
-            let id = r
-                .get("id")
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| "".to_string())
-                .trim_matches('"')
-                .to_string();
+        for route in list {
+            let mut r = warnings.record_by(&route, "id");
+            let id = r.record_id().to_string();
 
             routes.push(RouteData {
-                id,
-                group_id: to_string_opt(r.get("groupId")),
-                group_color: to_string_opt(r.get("groupColor")),
-                name: to_string_opt(r.get("name")),
-                short_name: to_string_opt(r.get("shortName")),
-                name_orig: to_string_opt(r.get("nameOrig")),
-                fullname: to_string_opt(r.get("fullname")),
-                myid: to_string_opt(r.get("myid")),
-                map_app: to_bool(r.get("mapApp")),
-                archive: to_bool(r.get("archive")),
-                go_prefix_route_name: to_bool(r.get("goPrefixRouteName")),
-                go_show_schedule: to_bool(r.get("goShowSchedule")),
-                outdated: to_bool(r.get("outdated")),
-                distance: to_f64(r.get("distance")),
-                latitude: to_f64(r.get("latitude")),
-                longitude: to_f64(r.get("longitude")),
-                timezone: to_string_opt(r.get("timezone")),
-                service_time: to_string_opt(r.get("serviceTime")),
-                service_time_short: to_string_opt(r.get("serviceTimeShort")),
-                system_id: to_i64(r.get("systemId")),
+                id: id.clone(),
+                group_id: r.string("groupId"),
+                group_color: r.string("groupColor"),
+                name: r.string("name"),
+                short_name: r.string("shortName"),
+                name_orig: r.string("nameOrig"),
+                fullname: r.string("fullname"),
+                myid: r.string("myid"),
+                map_app: r.bool("mapApp"),
+                archive: r.bool("archive"),
+                go_prefix_route_name: r.bool("goPrefixRouteName"),
+                go_show_schedule: r.bool("goShowSchedule"),
+                outdated: r.bool("outdated"),
+                distance: r.f64("distance"),
+                latitude: r.f64("latitude"),
+                longitude: r.f64("longitude"),
+                timezone: r.string("timezone"),
+                service_time: r.string("serviceTime"),
+                service_time_short: r.string("serviceTimeShort"),
+                system_id: r.i64("systemId"),
+                extra: self.extra_fields(&route, ROUTE_FIELDS),
             });
         }
 
+        tracing::Span::current().record("count", routes.len());
         Ok(routes)
     }
 
-    pub async fn get_buses(&self, system_id: i64) -> Result<Vec<VehicleData>, reqwest::Error> {
+    pub async fn get_buses(&self, system_id: i64) -> Result<Vec<VehicleData>, ApiError> {
+        self.fetch_buses(system_id, &mut ParseWarnings::default())
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "get_buses",
+        level = "debug",
+        skip(self, warnings),
+        fields(count = tracing::field::Empty)
+    )]
+    async fn fetch_buses(
+        &self,
+        system_id: i64,
+        warnings: &mut ParseWarnings,
+    ) -> Result<Vec<VehicleData>, ApiError> {
         let url = format!("{}/mapGetData.php?getBuses=2", self.base_url);
         let body = serde_json::json!({
             "s0": system_id.to_string(),
@@ -206,11 +389,15 @@ impl PassioGoClient {
             .and_then(|v| v.as_object())
             .cloned()
             .unwrap_or_default();
+        if data.get("buses").is_none() {
+            warn!("response has no `buses` object");
+        }
 
         let mut vehicles = Vec::new();
 
         for (bus_id, record) in buses {
             if bus_id == "-1" {
+                debug!("skipping placeholder bus entry \"-1\"");
                 continue;
             }
 
@@ -218,46 +405,85 @@ impl PassioGoClient {
 
             let v = match list.first() {
                 Some(v) => v,
-                None => continue,
+                None => {
+                    warn!(bus_id, "skipping bus entry without records");
+                    continue;
+                }
             };
 
-            let id = v
-                .get("busId")
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| bus_id.clone())
-                .trim_matches('"')
-                .to_string();
+            let mut r = warnings.record(&bus_id, v);
+            let id = r.required_string("busId").unwrap_or_else(|| bus_id.clone());
 
             vehicles.push(VehicleData {
-                id,
-                name: to_string_opt(v.get("busName")),
-                r#type: to_string_opt(v.get("busType")),
-                calculated_course: to_f64(v.get("calculatedCourse")),
-                route_id: to_string_opt(v.get("routeId")),
-                route_name: to_string_opt(v.get("route")),
-                color: to_string_opt(v.get("color")),
-                created: to_string_opt(v.get("created")),
-                latitude: to_f64(v.get("latitude")),
-                longitude: to_f64(v.get("longitude")),
-                speed: to_f64(v.get("speed")),
-                pax_load: to_f64(v.get("paxLoad")),
-                out_of_service: to_bool(v.get("outOfService")),
-                more: to_string_opt(v.get("more")),
-                trip_id: to_string_opt(v.get("tripId")),
+                id: id.clone(),
+                name: r.string("busName"),
+                r#type: r.string("busType"),
+                calculated_course: r.f64("calculatedCourse"),
+                route_id: r.string("routeId"),
+                route_name: r.string("route"),
+                color: r.string("color"),
+                created: r.string("created"),
+                latitude: r.f64("latitude"),
+                longitude: r.f64("longitude"),
+                speed: r.f64("speed"),
+                pax_load: r.f64("paxLoad"),
+                out_of_service: r.bool("outOfService"),
+                more: r.string("more"),
+                trip_id: r.string("tripId"),
+                extra: self.extra_fields(v, VEHICLE_FIELDS),
             });
         }
 
+        tracing::Span::current().record("count", vehicles.len());
         Ok(vehicles)
     }
 
-    pub async fn get_stops(&self, system_id: i64) -> Result<Vec<StopData>, reqwest::Error> {
+    pub async fn get_stops(&self, system_id: i64) -> Result<Vec<StopData>, ApiError> {
+        self.fetch_stops(system_id, &mut ParseWarnings::default())
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "get_stops",
+        level = "debug",
+        skip(self, warnings),
+        fields(count = tracing::field::Empty)
+    )]
+    async fn fetch_stops(
+        &self,
+        system_id: i64,
+        warnings: &mut ParseWarnings,
+    ) -> Result<Vec<StopData>, ApiError> {
+        let data = self.request_stop_data(system_id).await?;
+        let stops = self.parse_stops(&data, warnings);
+        tracing::Span::current().record("count", stops.len());
+        Ok(stops)
+    }
+
+    /// Stops and route shapes both come from `getStops`, so they can share one request.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn get_stops_and_shapes(
+        &self,
+        system_id: i64,
+    ) -> Result<(Vec<StopData>, Vec<RouteShapeData>), ApiError> {
+        let data = self.request_stop_data(system_id).await?;
+        let mut warnings = ParseWarnings::default();
+        Ok((
+            self.parse_stops(&data, &mut warnings),
+            self.parse_route_shapes(system_id, &data, &mut warnings),
+        ))
+    }
+
+    async fn request_stop_data(&self, system_id: i64) -> Result<Value, ApiError> {
         let url = format!("{}/mapGetData.php?getStops=2", self.base_url);
         let body = serde_json::json!({
             "s0": system_id.to_string(),
             "sA": 1
         });
-        let data = self.send_api_request(&url, Some(body)).await?;
+        self.send_api_request(&url, Some(body)).await
+    }
 
+    fn parse_stops(&self, data: &Value, warnings: &mut ParseWarnings) -> Vec<StopData> {
         let routes = data
             .get("routes")
             .and_then(|v| v.as_object())
@@ -269,6 +495,9 @@ impl PassioGoClient {
             .and_then(|v| v.as_object())
             .cloned()
             .unwrap_or_default();
+        if data.get("stops").is_none() {
+            warn!("response has no `stops` object");
+        }
 
         let mut routes_position_map: std::collections::HashMap<String, Vec<(f64, String)>> =
             std::collections::HashMap::new();
@@ -278,19 +507,22 @@ impl PassioGoClient {
             let mut entries = Vec::new();
             for item in list.iter().skip(2) {
                 let item_list = match item.as_array() {
-                    Some(a) => a,
-                    None => continue,
+                    Some(a) if a.len() >= 2 => a,
+                    _ => {
+                        debug!(route_id, raw = %item, "skipping malformed route stop entry");
+                        continue;
+                    }
                 };
-                if item_list.len() < 2 {
-                    continue;
-                }
                 let pos_val = &item_list[0];
                 let sid_val = &item_list[1];
                 let sid = to_string_opt(Some(sid_val)).unwrap_or_default();
                 if sid.is_empty() || sid == "0" {
                     continue;
                 }
-                let pos = to_f64(Some(pos_val)).unwrap_or(entries.len() as f64);
+                let pos = to_f64(Some(pos_val)).unwrap_or_else(|| {
+                    warnings.push(&route_id, "position", pos_val);
+                    entries.len() as f64
+                });
                 entries.push((pos, sid));
             }
             routes_position_map.insert(route_id, entries);
@@ -298,8 +530,9 @@ impl PassioGoClient {
 
         let mut stop_data = Vec::new();
 
-        for (id, stop) in stops {
-            let stop_id = to_string_opt(stop.get("id")).unwrap_or_else(|| id.clone());
+        for (key, stop) in stops {
+            let mut r = warnings.record(&key, &stop);
+            let stop_id = r.required_string("id").unwrap_or_else(|| key.clone());
             let mut routes_and_positions = std::collections::HashMap::new();
             for (route_id, entries) in routes_position_map.iter() {
                 let mut positions = Vec::new();
@@ -313,17 +546,104 @@ impl PassioGoClient {
                 }
             }
             stop_data.push(StopData {
-                id: stop_id,
+                id: stop_id.clone(),
                 routes_and_positions,
-                system_id: to_i64(stop.get("userId")),
-                name: to_string_opt(stop.get("name")),
-                latitude: to_f64(stop.get("latitude")),
-                longitude: to_f64(stop.get("longitude")),
-                radius: to_f64(stop.get("radius")),
+                system_id: r.i64("userId"),
+                name: r.string("name"),
+                latitude: r.f64("latitude"),
+                longitude: r.f64("longitude"),
+                radius: r.f64("radius"),
+                extra: self.extra_fields(&stop, STOP_FIELDS),
             });
         }
+        stop_data
+    }
+
+    pub async fn get_route_shapes(&self, system_id: i64) -> Result<Vec<RouteShapeData>, ApiError> {
+        self.fetch_route_shapes(system_id, &mut ParseWarnings::default())
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "get_route_shapes",
+        level = "debug",
+        skip(self, warnings),
+        fields(count = tracing::field::Empty)
+    )]
+    async fn fetch_route_shapes(
+        &self,
+        system_id: i64,
+        warnings: &mut ParseWarnings,
+    ) -> Result<Vec<RouteShapeData>, ApiError> {
+        let data = self.request_stop_data(system_id).await?;
+        let shapes = self.parse_route_shapes(system_id, &data, warnings);
+        tracing::Span::current().record("count", shapes.len());
+        Ok(shapes)
+    }
+
+    fn parse_route_shapes(
+        &self,
+        system_id: i64,
+        data: &Value,
+        warnings: &mut ParseWarnings,
+    ) -> Vec<RouteShapeData> {
+        let route_points = data
+            .get("routePoints")
+            .and_then(|v| v.as_object())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut shapes = Vec::new();
 
-        Ok(stop_data)
+        for (route_id, segments_val) in route_points {
+            let list = segments_val.as_array().cloned().unwrap_or_default();
+            let list = if list.first().is_some_and(|p| p.is_object()) {
+                vec![Value::Array(list)]
+            } else {
+                list
+            };
+            let mut segments = Vec::new();
+            let mut point_extras = Vec::new();
+            for segment in list {
+                let points = segment.as_array().cloned().unwrap_or_default();
+                let mut coords = Vec::new();
+                let mut extras = Vec::new();
+                for p in &points {
+                    let (lat, lon) = match p.as_array() {
+                        Some(pair) => (to_f64(pair.first()), to_f64(pair.get(1))),
+                        None => (to_f64(p.get("lat")), to_f64(p.get("lng"))),
+                    };
+                    let (Some(latitude), Some(longitude)) = (lat, lon) else {
+                        warnings.push(&route_id, "routePoints", p);
+                        continue;
+                    };
+                    coords.push(Coordinate {
+                        latitude,
+                        longitude,
+                    });
+                    extras.push(self.extra_fields(p, POINT_FIELDS));
+                }
+                if coords.len() >= 2 {
+                    segments.push(coords);
+                    point_extras.extend(extras);
+                } else {
+                    debug!(
+                        route_id,
+                        points = points.len(),
+                        "skipping route segment without two usable points"
+                    );
+                }
+            }
+            if !segments.is_empty() {
+                shapes.push(RouteShapeData {
+                    route_id,
+                    system_id,
+                    segments,
+                    extra: point_columns(point_extras),
+                });
+            }
+        }
+        shapes
     }
 
     pub async fn get_etas(
@@ -332,7 +652,31 @@ impl PassioGoClient {
         route_id: &String,
         position: &f64,
         system_id: &i64,
-    ) -> Result<Vec<ETAData>, reqwest::Error> {
+    ) -> Result<Vec<ETAData>, ApiError> {
+        self.fetch_etas(
+            stop_id,
+            route_id,
+            position,
+            system_id,
+            &mut ParseWarnings::default(),
+        )
+        .await
+    }
+
+    #[tracing::instrument(
+        name = "get_etas",
+        level = "debug",
+        skip(self, warnings),
+        fields(count = tracing::field::Empty)
+    )]
+    async fn fetch_etas(
+        &self,
+        stop_id: &String,
+        route_id: &String,
+        position: &f64,
+        system_id: &i64,
+        warnings: &mut ParseWarnings,
+    ) -> Result<Vec<ETAData>, ApiError> {
         let url = format!(
             "{}/mapGetData.php?eta=3&stopIds={}&routeId={}&userId={}&position={}",
             self.base_url, stop_id, route_id, system_id, position
@@ -354,40 +698,17 @@ impl PassioGoClient {
         let mut etas: Vec<ETAData> = vec![];
 
         for eta in list {
+            let mut r = warnings.record_by(&eta, "busName");
             etas.push(ETAData {
-                bus_name: eta
-                    .get("busName")
-                    .unwrap_or_default()
-                    .to_string()
-                    .trim_matches('"')
-                    .to_string(),
-                eta: eta
-                    .get("eta")
-                    .unwrap_or_default()
-                    .to_string()
-                    .trim_matches('"')
-                    .to_string(),
+                bus_name: r.record_id().to_string(),
+                eta: r.required_string("eta").unwrap_or_default(),
                 eta_note: to_string_opt(eta.get("etaNote")),
-                go_show_schedule: eta.get("goShowSchedule").unwrap_or_default().as_i64(),
-                order: eta.get("order").unwrap_or_default().as_i64(),
-                out_of_service: eta
-                    .get("outOfService")
-                    .unwrap_or_default()
-                    .as_bool()
-                    .unwrap_or(false),
-                reason: eta
-                    .get("reason")
-                    .unwrap_or_default()
-                    .to_string()
-                    .trim_matches('"')
-                    .to_string(),
-                route_id: eta
-                    .get("routeId")
-                    .unwrap_or_default()
-                    .to_string()
-                    .trim_matches('"')
-                    .to_string(),
-                schedule_not_empty: eta.get("scheduleNotEmpty").unwrap_or_default().as_i64(),
+                go_show_schedule: r.i64("goShowSchedule"),
+                order: r.i64("order"),
+                out_of_service: r.bool("outOfService").unwrap_or(false),
+                reason: r.required_string("reason").unwrap_or_default(),
+                route_id: r.required_string("routeId").unwrap_or_default(),
+                schedule_not_empty: r.i64("scheduleNotEmpty"),
                 schedule_time: to_string_opt(eta.get("scheduleTime")),
                 schedule_times: Some(
                     eta.get("scheduleTimes")
@@ -398,14 +719,165 @@ impl PassioGoClient {
                         .map(|v| v.to_string())
                         .collect::<Vec<String>>(),
                 ),
-                seconds_spent: eta
-                    .get("secondsSpent")
-                    .unwrap_or_default()
-                    .as_i64()
-                    .unwrap_or_default(),
+                seconds_spent: r.i64("secondsSpent").unwrap_or_default(),
+                extra: self.extra_fields(&eta, ETA_FIELDS),
             });
         }
 
+        tracing::Span::current().record("count", etas.len());
         Ok(etas)
     }
 }
+
+/// Turns per-point extra fields into one array per key, aligned with the route's points
+/// in segment order and holding null where a point lacks the key.
+fn point_columns(points: Vec<Map<String, Value>>) -> Map<String, Value> {
+    let mut columns = Map::new();
+    let count = points.len();
+    for (i, fields) in points.into_iter().enumerate() {
+        for (key, value) in fields {
+            if let Value::Array(values) = columns
+                .entry(key)
+                .or_insert_with(|| Value::Array(vec![Value::Null; count]))
+            {
+                values[i] = value;
+            }
+        }
+    }
+    columns
+}
+
+// Payload keys read into each type's named fields; everything else goes to `extra`.
+const SYSTEM_FIELDS: &[&str] = &[
+    "id",
+    "fullname",
+    "username",
+    "goAgencyName",
+    "email",
+    "goTestMode",
+    "name2",
+    "homepage",
+    "logo",
+    "goRoutePlannerEnabled",
+    "goColor",
+    "goSupportEmail",
+    "goSharedCode",
+    "goAuthenticationType",
+];
+
+const ALERT_FIELDS: &[&str] = &[
+    "id",
+    "userId",
+    "routeId",
+    "name",
+    "html",
+    "archive",
+    "important",
+    "created",
+    "from",
+    "to",
+    "asPush",
+    "gtfs",
+    "gtfsAlertCauseId",
+    "gtfsAlertEffectId",
+    "gtfsAlertUrl",
+    "gtfsAlertHeaderText",
+    "gtfsAlertDescriptionText",
+    "routeGroupId",
+    "createdUtc",
+    "authorId",
+    "author",
+    "updated",
+    "updateAuthorId",
+    "updateAuthor",
+    "createdF",
+    "fromF",
+    "fromOk",
+    "toOk",
+];
+
+const ROUTE_FIELDS: &[&str] = &[
+    "id",
+    "groupId",
+    "groupColor",
+    "name",
+    "shortName",
+    "nameOrig",
+    "fullname",
+    "myid",
+    "mapApp",
+    "archive",
+    "goPrefixRouteName",
+    "goShowSchedule",
+    "outdated",
+    "distance",
+    "latitude",
+    "longitude",
+    "timezone",
+    "serviceTime",
+    "serviceTimeShort",
+    "systemId",
+];
+
+const VEHICLE_FIELDS: &[&str] = &[
+    "busId",
+    "busName",
+    "busType",
+    "calculatedCourse",
+    "routeId",
+    "route",
+    "color",
+    "created",
+    "latitude",
+    "longitude",
+    "speed",
+    "paxLoad",
+    "outOfService",
+    "more",
+    "tripId",
+];
+
+const POINT_FIELDS: &[&str] = &["lat", "lng"];
+
+const STOP_FIELDS: &[&str] = &["id", "userId", "name", "latitude", "longitude", "radius"];
+
+const ETA_FIELDS: &[&str] = &[
+    "busName",
+    "eta",
+    "etaNote",
+    "goShowSchedule",
+    "order",
+    "outOfService",
+    "reason",
+    "routeId",
+    "scheduleNotEmpty",
+    "scheduleTime",
+    "scheduleTimes",
+    "secondsSpent",
+];
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn point_columns_align_with_points() {
+        let points = [
+            json!({ "lat": 1.0, "lng": 2.0, "stopId": "a" }),
+            json!({ "lat": 1.5, "lng": 2.5 }),
+            json!({ "lat": 2.0, "lng": 3.0, "stopId": "b", "bearing": 90 }),
+        ];
+        let client = PassioGoClient::new().keep_extra_fields();
+        let extras = points
+            .iter()
+            .map(|p| client.extra_fields(p, POINT_FIELDS))
+            .collect();
+        assert_eq!(
+            Value::Object(point_columns(extras)),
+            json!({ "stopId": ["a", null, "b"], "bearing": [null, null, 90] })
+        );
+        assert!(point_columns(vec![Map::new(); 3]).is_empty());
+    }
+}
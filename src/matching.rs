@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use crate::geo::{polyline_length, project_onto_polyline};
+use crate::route_stops::stops_for_route;
+use crate::types::{Coordinate, RouteShapeData, StopData, VehicleData};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleProgress {
+    pub vehicle_id: String,
+    pub route_id: String,
+    pub snapped: Coordinate,
+    pub along_meters: f64,
+    pub route_length_meters: f64,
+    pub offset_meters: f64,
+    pub off_route: bool,
+    pub next_stop_id: Option<String>,
+    pub distance_to_next_stop: Option<f64>,
+}
+
+pub(crate) struct MatchedRoute {
+    pub(crate) line: Vec<Coordinate>,
+    pub(crate) length: f64,
+    pub(crate) stops: Vec<(f64, String)>,
+}
+
+/// Snaps vehicle positions onto their route's shape.
+pub struct RouteMatcher {
+    routes: HashMap<String, MatchedRoute>,
+    off_route_threshold: f64,
+}
+
+impl RouteMatcher {
+    pub fn new(shapes: &[RouteShapeData], stops: &[StopData]) -> Self {
+        let mut routes = HashMap::new();
+        for shape in shapes {
+            let line: Vec<Coordinate> = shape.segments.iter().flatten().copied().collect();
+            if line.len() < 2 {
+                continue;
+            }
+            let stops = stops_along(&line, &shape.route_id, stops);
+            routes.insert(
+                shape.route_id.clone(),
+                MatchedRoute {
+                    length: polyline_length(&line),
+                    line,
+                    stops,
+                },
+            );
+        }
+        Self {
+            routes,
+            off_route_threshold: 100.0,
+        }
+    }
+
+    pub fn off_route_threshold(mut self, meters: f64) -> Self {
+        self.off_route_threshold = meters;
+        self
+    }
+
+    pub(crate) fn route(&self, route_id: &str) -> Option<&MatchedRoute> {
+        self.routes.get(route_id)
+    }
+
+    pub fn progress(&self, vehicle: &VehicleData) -> Option<VehicleProgress> {
+        let route_id = vehicle.route_id.as_ref()?;
+        let route = self.routes.get(route_id)?;
+        let position = Coordinate {
+            latitude: vehicle.latitude?,
+            longitude: vehicle.longitude?,
+        };
+        let projection = project_onto_polyline(&route.line, position)?;
+        let along = projection.along_meters;
+
+        // Past the last stop the vehicle is heading back round to the first one.
+        let next = route
+            .stops
+            .iter()
+            .find(|(stop_along, _)| *stop_along >= along)
+            .map(|(stop_along, id)| (id.clone(), stop_along - along))
+            .or_else(|| {
+                route
+                    .stops
+                    .first()
+                    .map(|(stop_along, id)| (id.clone(), route.length - along + stop_along))
+            });
+
+        Some(VehicleProgress {
+            vehicle_id: vehicle.id.clone(),
+            route_id: route_id.clone(),
+            snapped: projection.point,
+            along_meters: along,
+            route_length_meters: route.length,
+            offset_meters: projection.offset_meters,
+            off_route: projection.offset_meters > self.off_route_threshold,
+            next_stop_id: next.as_ref().map(|(id, _)| id.clone()),
+            distance_to_next_stop: next.map(|(_, d)| d),
+        })
+    }
+
+    pub fn match_all(&self, vehicles: &[VehicleData]) -> Vec<VehicleProgress> {
+        vehicles.iter().filter_map(|v| self.progress(v)).collect()
+    }
+
+    pub fn off_route(&self, vehicles: &[VehicleData]) -> Vec<VehicleProgress> {
+        self.match_all(vehicles)
+            .into_iter()
+            .filter(|p| p.off_route)
+            .collect()
+    }
+}
+
+// Stops are placed in `routes_and_positions` order, each searched for only past the previous
+// one, so a loop's shared first/last stop lands at both ends of the line.
+fn stops_along(line: &[Coordinate], route_id: &str, stops: &[StopData]) -> Vec<(f64, String)> {
+    let ordered = stops_for_route(stops, route_id);
+
+    let mut placed = Vec::new();
+    let mut from_segment = 0;
+    let mut along_before = 0.0;
+    for route_stop in ordered.stops {
+        let (Some(latitude), Some(longitude)) =
+            (route_stop.stop.latitude, route_stop.stop.longitude)
+        else {
+            continue;
+        };
+        let coordinate = Coordinate {
+            latitude,
+            longitude,
+        };
+        let tail = &line[from_segment..];
+        let Some(projection) = project_onto_polyline(tail, coordinate) else {
+            break;
+        };
+        let along = along_before + projection.along_meters;
+        placed.push((along, route_stop.stop.id.clone()));
+
+        along_before += polyline_length(&tail[..=projection.segment]);
+        from_segment += projection.segment;
+    }
+    placed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 1 km square loop A → B → C → D → A.
+    fn at(north: f64, east: f64) -> Coordinate {
+        Coordinate {
+            latitude: 41.0 + north / 111_195.0,
+            longitude: -87.0 + east / (111_195.0 * 41f64.to_radians().cos()),
+        }
+    }
+
+    fn stop(id: &str, corner: Coordinate, positions: &[f64]) -> StopData {
+        StopData {
+            id: id.to_string(),
+            latitude: Some(corner.latitude),
+            longitude: Some(corner.longitude),
+            routes_and_positions: [("1".to_string(), positions.to_vec())].into(),
+            ..Default::default()
+        }
+    }
+
+    fn square_stops() -> Vec<StopData> {
+        vec![
+            stop("A", at(0.0, 0.0), &[0.0, 4.0]),
+            stop("B", at(1000.0, 0.0), &[1.0]),
+            stop("C", at(1000.0, 1000.0), &[2.0]),
+            stop("D", at(0.0, 1000.0), &[3.0]),
+        ]
+    }
+
+    fn square_shape() -> RouteShapeData {
+        RouteShapeData {
+            route_id: "1".to_string(),
+            segments: vec![
+                vec![at(0.0, 0.0), at(1000.0, 0.0), at(1000.0, 1000.0)],
+                vec![at(1000.0, 1000.0), at(0.0, 1000.0), at(0.0, 0.0)],
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn bus(position: Coordinate) -> VehicleData {
+        VehicleData {
+            id: "bus".to_string(),
+            route_id: Some("1".to_string()),
+            latitude: Some(position.latitude),
+            longitude: Some(position.longitude),
+            ..Default::default()
+        }
+    }
+
+    fn rounded(placed: &[(f64, String)]) -> Vec<(f64, &str)> {
+        placed
+            .iter()
+            .map(|(along, id)| ((along / 10.0).round() * 10.0, id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn loop_start_is_placed_at_both_ends() {
+        let line: Vec<Coordinate> = square_shape().segments.concat();
+        assert_eq!(
+            rounded(&stops_along(&line, "1", &square_stops())),
+            [
+                (0.0, "A"),
+                (1000.0, "B"),
+                (2000.0, "C"),
+                (3000.0, "D"),
+                (4000.0, "A"),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_are_placed_in_travel_order() {
+        // B sits on the line's first stretch, but it comes after A, so it is placed on the
+        // closest point past A instead.
+        let line = vec![
+            at(0.0, 0.0),
+            at(0.0, 500.0),
+            at(1000.0, 500.0),
+            at(1000.0, 0.0),
+            at(0.0, 0.0),
+        ];
+        let stops = vec![
+            stop("A", at(1000.0, 500.0), &[0.0]),
+            stop("B", at(0.0, 200.0), &[1.0]),
+        ];
+        assert_eq!(
+            rounded(&stops_along(&line, "1", &stops)),
+            [(1500.0, "A"), (3000.0, "B")]
+        );
+    }
+
+    #[test]
+    fn progress_finds_the_next_stop() {
+        let matcher = RouteMatcher::new(&[square_shape()], &square_stops());
+        let progress = matcher.progress(&bus(at(400.0, 10.0))).unwrap();
+        assert_eq!(progress.next_stop_id.as_deref(), Some("B"));
+        assert!((progress.along_meters - 400.0).abs() < 5.0);
+        assert!((progress.distance_to_next_stop.unwrap() - 600.0).abs() < 5.0);
+        assert!((progress.route_length_meters - 4000.0).abs() < 5.0);
+        assert!((progress.offset_meters - 10.0).abs() < 1.0);
+        assert!(!progress.off_route);
+
+        // On the last side the loop's start is next.
+        let closing = matcher.progress(&bus(at(0.0, 300.0))).unwrap();
+        assert_eq!(closing.next_stop_id.as_deref(), Some("A"));
+        assert!((closing.distance_to_next_stop.unwrap() - 300.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn past_the_last_stop_wraps_to_the_first() {
+        let stops: Vec<StopData> = square_stops()
+            .into_iter()
+            .filter(|s| s.id != "D")
+            .map(|mut s| {
+                s.routes_and_positions.insert("1".to_string(), vec![0.0]);
+                s
+            })
+            .collect();
+        let matcher = RouteMatcher::new(&[square_shape()], &stops);
+        let progress = matcher.progress(&bus(at(500.0, 1000.0))).unwrap();
+        assert_eq!(progress.next_stop_id.as_deref(), Some("A"));
+        assert!((progress.distance_to_next_stop.unwrap() - 1500.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn flags_vehicles_off_route() {
+        let matcher =
+            RouteMatcher::new(&[square_shape()], &square_stops()).off_route_threshold(50.0);
+        let detour = bus(at(500.0, 200.0));
+        let on_route = bus(at(500.0, 20.0));
+        let off: Vec<f64> = matcher
+            .off_route(&[detour, on_route])
+            .iter()
+            .map(|p| p.offset_meters.round())
+            .collect();
+        assert_eq!(off, [200.0]);
+
+        let unknown = VehicleData {
+            route_id: Some("2".to_string()),
+            ..bus(at(0.0, 0.0))
+        };
+        assert_eq!(matcher.progress(&unknown), None);
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tracing::warn;
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::helpers::endpoint_label;
+use crate::types::{SystemAlertData, VehicleData};
+
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct RouteFleet {
+    in_service: usize,
+    speed_sum: f64,
+    speed_count: usize,
+}
+
+#[derive(Default)]
+struct SystemFleet {
+    routes: BTreeMap<String, RouteFleet>,
+    out_of_service: usize,
+    alerts: Option<usize>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct EndpointStats {
+    requests: u64,
+    errors: u64,
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    seconds_sum: f64,
+}
+
+#[derive(Default)]
+struct State {
+    systems: BTreeMap<i64, SystemFleet>,
+    endpoints: BTreeMap<String, EndpointStats>,
+}
+
+/// Process-wide fleet gauges and client request metrics, rendered in the Prometheus text
+/// format. Every `PassioGoClient` request is recorded here; fleet gauges are refreshed
+/// with `record_vehicles` and `record_alerts` (or `PassioGoClient::refresh_fleet_metrics`).
+pub struct MetricsRegistry {
+    state: Mutex<State>,
+}
+
+impl MetricsRegistry {
+    pub fn global() -> &'static MetricsRegistry {
+        static REGISTRY: OnceLock<MetricsRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(|| MetricsRegistry {
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    /// Replaces a system's vehicle gauges with those of one `get_buses` result.
+    pub fn record_vehicles(&self, system_id: i64, vehicles: &[VehicleData]) {
+        let mut fleet = SystemFleet::default();
+        for vehicle in vehicles {
+            if vehicle.out_of_service.unwrap_or(false) {
+                fleet.out_of_service += 1;
+                continue;
+            }
+            let route = fleet
+                .routes
+                .entry(vehicle.route_id.clone().unwrap_or_default())
+                .or_default();
+            route.in_service += 1;
+            if let Some(speed) = vehicle.speed.filter(|s| s.is_finite()) {
+                route.speed_sum += speed;
+                route.speed_count += 1;
+            }
+        }
+        fleet.updated_at = Some(Utc::now());
+
+        let mut state = self.lock();
+        fleet.alerts = state.systems.get(&system_id).and_then(|s| s.alerts);
+        state.systems.insert(system_id, fleet);
+    }
+
+    pub fn record_alerts(&self, system_id: i64, alerts: &[SystemAlertData]) {
+        let active = alerts
+            .iter()
+            .filter(|a| !a.archive.unwrap_or(false))
+            .count();
+        self.lock().systems.entry(system_id).or_default().alerts = Some(active);
+    }
+
+    pub(crate) fn record_request(&self, url: &str, elapsed: Duration, ok: bool) {
+        let mut state = self.lock();
+        let stats = state.endpoints.entry(endpoint_label(url)).or_default();
+        let seconds = elapsed.as_secs_f64();
+        stats.requests += 1;
+        if !ok {
+            stats.errors += 1;
+        }
+        stats.seconds_sum += seconds;
+        for (count, bound) in stats.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *count += 1;
+            }
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let state = self.lock();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "passiogo_vehicles_in_service",
+            "gauge",
+            "Vehicles in service per route.",
+        );
+        for (system_id, fleet) in &state.systems {
+            for (route_id, route) in &fleet.routes {
+                let _ = writeln!(
+                    out,
+                    "passiogo_vehicles_in_service{{system_id=\"{}\",route_id=\"{}\"}} {}",
+                    system_id,
+                    escape(route_id),
+                    route.in_service
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "passiogo_vehicles_out_of_service",
+            "gauge",
+            "Vehicles reported out of service.",
+        );
+        for (system_id, fleet) in &state.systems {
+            let _ = writeln!(
+                out,
+                "passiogo_vehicles_out_of_service{{system_id=\"{}\"}} {}",
+                system_id, fleet.out_of_service
+            );
+        }
+
+        header(
+            &mut out,
+            "passiogo_vehicle_average_speed",
+            "gauge",
+            "Average reported speed of in-service vehicles per route.",
+        );
+        for (system_id, fleet) in &state.systems {
+            for (route_id, route) in &fleet.routes {
+                if route.speed_count > 0 {
+                    let _ = writeln!(
+                        out,
+                        "passiogo_vehicle_average_speed{{system_id=\"{}\",route_id=\"{}\"}} {}",
+                        system_id,
+                        escape(route_id),
+                        route.speed_sum / route.speed_count as f64
+                    );
+                }
+            }
+        }
+
+        header(
+            &mut out,
+            "passiogo_alerts",
+            "gauge",
+            "Active (non-archived) alerts.",
+        );
+        for (system_id, fleet) in &state.systems {
+            if let Some(alerts) = fleet.alerts {
+                let _ = writeln!(
+                    out,
+                    "passiogo_alerts{{system_id=\"{}\"}} {}",
+                    system_id, alerts
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "passiogo_fleet_updated_timestamp_seconds",
+            "gauge",
+            "When the vehicle gauges were last refreshed.",
+        );
+        for (system_id, fleet) in &state.systems {
+            if let Some(updated_at) = fleet.updated_at {
+                let _ = writeln!(
+                    out,
+                    "passiogo_fleet_updated_timestamp_seconds{{system_id=\"{}\"}} {}",
+                    system_id,
+                    updated_at.timestamp()
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "passiogo_requests_total",
+            "counter",
+            "Passio API requests sent.",
+        );
+        for (endpoint, stats) in &state.endpoints {
+            let _ = writeln!(
+                out,
+                "passiogo_requests_total{{endpoint=\"{}\"}} {}",
+                escape(endpoint),
+                stats.requests
+            );
+        }
+
+        header(
+            &mut out,
+            "passiogo_request_errors_total",
+            "counter",
+            "Passio API requests that failed to send or decode.",
+        );
+        for (endpoint, stats) in &state.endpoints {
+            let _ = writeln!(
+                out,
+                "passiogo_request_errors_total{{endpoint=\"{}\"}} {}",
+                escape(endpoint),
+                stats.errors
+            );
+        }
+
+        header(
+            &mut out,
+            "passiogo_request_duration_seconds",
+            "histogram",
+            "Passio API request latency.",
+        );
+        for (endpoint, stats) in &state.endpoints {
+            let endpoint = escape(endpoint);
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(stats.buckets) {
+                let _ = writeln!(
+                    out,
+                    "passiogo_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}",
+                    endpoint, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "passiogo_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}",
+                endpoint, stats.requests
+            );
+            let _ = writeln!(
+                out,
+                "passiogo_request_duration_seconds_sum{{endpoint=\"{}\"}} {}",
+                endpoint, stats.seconds_sum
+            );
+            let _ = writeln!(
+                out,
+                "passiogo_request_duration_seconds_count{{endpoint=\"{}\"}} {}",
+                endpoint, stats.requests
+            );
+        }
+
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl PassioGoClient {
+    /// Fetches a system's vehicles and alerts into the global `MetricsRegistry`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn refresh_fleet_metrics(&self, system_id: i64) -> Result<(), ApiError> {
+        let (vehicles, alerts) =
+            tokio::try_join!(self.get_buses(system_id), self.get_alerts(system_id))?;
+        let registry = MetricsRegistry::global();
+        registry.record_vehicles(system_id, &vehicles);
+        registry.record_alerts(system_id, &alerts);
+        Ok(())
+    }
+}
+
+/// Serves the global registry at `GET /metrics`. Only fails if `addr` cannot be bound;
+/// connections that cannot be accepted (e.g. when out of file descriptors) are logged and
+/// skipped.
+pub async fn serve_metrics(addr: impl ToSocketAddrs) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                warn!(error = %e, "failed to accept metrics connection");
+                // Errors like EMFILE persist until something is released; don't spin on them.
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+                match stream.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let line = String::from_utf8_lossy(&request);
+            let path = line.split_whitespace().nth(1).unwrap_or_default();
+            let response = if line.starts_with("GET ") && path.split('?').next() == Some("/metrics")
+            {
+                let body = MetricsRegistry::global().render();
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string()
+            };
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        });
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> MetricsRegistry {
+        MetricsRegistry {
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn bus(route: &str, speed: Option<f64>, out_of_service: bool) -> VehicleData {
+        VehicleData {
+            id: "bus".to_string(),
+            route_id: Some(route.to_string()),
+            speed,
+            out_of_service: Some(out_of_service),
+            ..Default::default()
+        }
+    }
+
+    fn alert(archive: bool) -> SystemAlertData {
+        SystemAlertData {
+            id: "alert".to_string(),
+            archive: Some(archive),
+            ..Default::default()
+        }
+    }
+
+    fn lines(out: &str) -> Vec<&str> {
+        out.lines().filter(|l| !l.starts_with('#')).collect()
+    }
+
+    #[test]
+    fn renders_fleet_gauges() {
+        let registry = registry();
+        registry.record_alerts(7, &[alert(false), alert(true), alert(false)]);
+        registry.record_vehicles(
+            7,
+            &[
+                bus("R1", Some(10.0), false),
+                bus("R1", Some(20.0), false),
+                bus("R1", None, false),
+                bus("R\"2", None, false),
+                bus("R1", Some(99.0), true),
+            ],
+        );
+
+        let out = registry.render();
+        let samples = lines(&out);
+        assert!(
+            samples.contains(&"passiogo_vehicles_in_service{system_id=\"7\",route_id=\"R1\"} 3")
+        );
+        assert!(
+            samples
+                .contains(&"passiogo_vehicles_in_service{system_id=\"7\",route_id=\"R\\\"2\"} 1")
+        );
+        assert!(samples.contains(&"passiogo_vehicles_out_of_service{system_id=\"7\"} 1"));
+        // Out-of-service and speedless vehicles don't count towards the average.
+        assert!(
+            samples.contains(&"passiogo_vehicle_average_speed{system_id=\"7\",route_id=\"R1\"} 15")
+        );
+        assert!(!samples.iter().any(|l| {
+            l.starts_with("passiogo_vehicle_average_speed{system_id=\"7\",route_id=\"R\\\"2")
+        }));
+        // Alerts recorded before the vehicles survive the vehicle refresh.
+        assert!(samples.contains(&"passiogo_alerts{system_id=\"7\"} 2"));
+        assert!(
+            samples.iter().any(
+                |l| l.starts_with("passiogo_fleet_updated_timestamp_seconds{system_id=\"7\"} ")
+            )
+        );
+        assert!(out.contains("# TYPE passiogo_vehicles_in_service gauge\n"));
+    }
+
+    #[test]
+    fn renders_request_histogram() {
+        let registry = registry();
+        let url = "https://passio3.com/mapGetData.php?getBuses=2&wTransloc=1";
+        registry.record_request(url, Duration::from_millis(80), true);
+        registry.record_request(url, Duration::from_millis(300), false);
+        registry.record_request(url, Duration::from_secs(20), true);
+
+        let out = registry.render();
+        let samples = lines(&out);
+        let endpoint = "endpoint=\"mapGetData.php?getBuses\"";
+        let expected = [
+            format!("passiogo_requests_total{{{}}} 3", endpoint),
+            format!("passiogo_request_errors_total{{{}}} 1", endpoint),
+            format!(
+                "passiogo_request_duration_seconds_bucket{{{},le=\"0.05\"}} 0",
+                endpoint
+            ),
+            format!(
+                "passiogo_request_duration_seconds_bucket{{{},le=\"0.1\"}} 1",
+                endpoint
+            ),
+            format!(
+                "passiogo_request_duration_seconds_bucket{{{},le=\"0.5\"}} 2",
+                endpoint
+            ),
+            format!(
+                "passiogo_request_duration_seconds_bucket{{{},le=\"10\"}} 2",
+                endpoint
+            ),
+            format!(
+                "passiogo_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 3",
+                endpoint
+            ),
+            format!("passiogo_request_duration_seconds_count{{{}}} 3", endpoint),
+        ];
+        for line in &expected {
+            assert!(samples.contains(&line.as_str()), "missing {}", line);
+        }
+        assert!(out.contains("# TYPE passiogo_request_duration_seconds histogram\n"));
+    }
+
+    #[test]
+    fn renders_headers_when_empty() {
+        let out = registry().render();
+        assert!(lines(&out).is_empty());
+        assert_eq!(out.matches("# TYPE ").count(), 8);
+    }
+}
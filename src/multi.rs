@@ -0,0 +1,171 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::task::JoinSet;
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::types::{RouteData, StopData, SystemAlertData, VehicleData};
+
+#[derive(Debug, Clone)]
+pub struct Tagged<T> {
+    pub system_id: i64,
+    pub data: T,
+}
+
+#[derive(Debug)]
+pub enum SystemFetchError {
+    Request(ApiError),
+    TimedOut(Duration),
+    Panicked,
+}
+
+impl fmt::Display for SystemFetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemFetchError::Request(e) => write!(f, "request failed: {}", e),
+            SystemFetchError::TimedOut(after) => write!(f, "timed out after {:?}", after),
+            SystemFetchError::Panicked => write!(f, "fetch task panicked"),
+        }
+    }
+}
+
+impl std::error::Error for SystemFetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SystemFetchError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MultiSystemResult<T> {
+    pub data: Vec<Tagged<T>>,
+    pub errors: Vec<(i64, SystemFetchError)>,
+}
+
+impl<T> MultiSystemResult<T> {
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn for_system(&self, system_id: i64) -> impl Iterator<Item = &T> {
+        self.data
+            .iter()
+            .filter(move |t| t.system_id == system_id)
+            .map(|t| &t.data)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiSystemClient {
+    client: PassioGoClient,
+    system_ids: Vec<i64>,
+    timeout: Option<Duration>,
+}
+
+impl MultiSystemClient {
+    pub fn new(system_ids: impl IntoIterator<Item = i64>) -> Self {
+        Self::with_client(PassioGoClient::new(), system_ids)
+    }
+
+    pub fn with_client(client: PassioGoClient, system_ids: impl IntoIterator<Item = i64>) -> Self {
+        let mut ids: Vec<i64> = Vec::new();
+        for id in system_ids {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        Self {
+            client,
+            system_ids: ids,
+            timeout: Some(Duration::from_secs(15)),
+        }
+    }
+
+    /// Per-system deadline; systems that miss it are reported in `errors`. `None` waits forever.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn system_ids(&self) -> &[i64] {
+        &self.system_ids
+    }
+
+    pub async fn get_buses(&self) -> MultiSystemResult<VehicleData> {
+        self.fan_out(|client, id| async move { client.get_buses(id).await })
+            .await
+    }
+
+    pub async fn get_stops(&self) -> MultiSystemResult<StopData> {
+        self.fan_out(|client, id| async move { client.get_stops(id).await })
+            .await
+    }
+
+    pub async fn get_routes(&self) -> MultiSystemResult<RouteData> {
+        self.fan_out(|client, id| async move { client.get_routes(id).await })
+            .await
+    }
+
+    pub async fn get_alerts(&self) -> MultiSystemResult<SystemAlertData> {
+        self.fan_out(|client, id| async move { client.get_alerts(id).await })
+            .await
+    }
+
+    async fn fan_out<T, F, Fut>(&self, fetch: F) -> MultiSystemResult<T>
+    where
+        T: Send + 'static,
+        F: Fn(PassioGoClient, i64) -> Fut,
+        Fut: Future<Output = Result<Vec<T>, ApiError>> + Send + 'static,
+    {
+        let mut tasks = JoinSet::new();
+        let mut pending: Vec<Option<i64>> = Vec::new();
+        for (idx, &system_id) in self.system_ids.iter().enumerate() {
+            let request = fetch(self.client.clone(), system_id);
+            let timeout = self.timeout;
+            pending.push(Some(system_id));
+            tasks.spawn(async move {
+                let result = match timeout {
+                    Some(after) => match tokio::time::timeout(after, request).await {
+                        Ok(result) => result.map_err(SystemFetchError::Request),
+                        Err(_) => Err(SystemFetchError::TimedOut(after)),
+                    },
+                    None => request.await.map_err(SystemFetchError::Request),
+                };
+                (idx, result)
+            });
+        }
+
+        let mut results: Vec<(usize, Result<Vec<T>, SystemFetchError>)> = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((idx, result)) = joined {
+                pending[idx] = None;
+                results.push((idx, result));
+            }
+        }
+        results.sort_by_key(|(idx, _)| *idx);
+
+        let mut data = Vec::new();
+        let mut errors = Vec::new();
+        for (idx, result) in results {
+            let system_id = self.system_ids[idx];
+            match result {
+                Ok(records) => {
+                    data.extend(records.into_iter().map(|d| Tagged { system_id, data: d }))
+                }
+                Err(e) => errors.push((system_id, e)),
+            }
+        }
+        errors.extend(
+            pending
+                .into_iter()
+                .flatten()
+                .map(|system_id| (system_id, SystemFetchError::Panicked)),
+        );
+
+        MultiSystemResult { data, errors }
+    }
+}
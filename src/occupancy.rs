@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::types::VehicleData;
+
+/// Crowding levels named after GTFS-Realtime `OccupancyStatus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Occupancy {
+    Empty,
+    ManySeatsAvailable,
+    FewSeatsAvailable,
+    StandingRoomOnly,
+    Full,
+}
+
+impl Occupancy {
+    pub const ALL: [Occupancy; 5] = [
+        Occupancy::Empty,
+        Occupancy::ManySeatsAvailable,
+        Occupancy::FewSeatsAvailable,
+        Occupancy::StandingRoomOnly,
+        Occupancy::Full,
+    ];
+
+    /// The GTFS-Realtime `OccupancyStatus` enum value.
+    pub fn gtfs_rt_value(self) -> i32 {
+        match self {
+            Occupancy::Empty => 0,
+            Occupancy::ManySeatsAvailable => 1,
+            Occupancy::FewSeatsAvailable => 2,
+            Occupancy::StandingRoomOnly => 3,
+            Occupancy::Full => 5,
+        }
+    }
+
+    pub fn gtfs_rt_name(self) -> &'static str {
+        match self {
+            Occupancy::Empty => "EMPTY",
+            Occupancy::ManySeatsAvailable => "MANY_SEATS_AVAILABLE",
+            Occupancy::FewSeatsAvailable => "FEW_SEATS_AVAILABLE",
+            Occupancy::StandingRoomOnly => "STANDING_ROOM_ONLY",
+            Occupancy::Full => "FULL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteOccupancy {
+    pub route_id: String,
+    pub vehicles: usize,
+    /// Vehicles that reported a `pax_load`.
+    pub reporting: usize,
+    pub passengers: f64,
+    pub capacity: f64,
+    /// `passengers / capacity` over the reporting vehicles.
+    pub load_factor: Option<f64>,
+    pub counts: HashMap<Occupancy, usize>,
+    pub most_crowded: Option<Occupancy>,
+}
+
+/// Maps `pax_load` to an [`Occupancy`] level.
+///
+/// By default `pax_load` is read as a passenger count and divided by the vehicle's capacity,
+/// looked up by `r#type` with a fallback to `default_capacity`. Capacities differ between
+/// systems, so keep one model per system. Systems that report a percentage instead should
+/// use `load_in_percent`.
+#[derive(Debug, Clone)]
+pub struct OccupancyModel {
+    default_capacity: f64,
+    capacities: HashMap<String, f64>,
+    percent: bool,
+    thresholds: [f64; 4],
+}
+
+impl Default for OccupancyModel {
+    fn default() -> Self {
+        Self {
+            default_capacity: 40.0,
+            capacities: HashMap::new(),
+            percent: false,
+            thresholds: [0.05, 0.5, 0.8, 1.0],
+        }
+    }
+}
+
+impl OccupancyModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn default_capacity(mut self, passengers: f64) -> Self {
+        self.default_capacity = passengers;
+        self
+    }
+
+    /// Capacity for vehicles whose `r#type` matches `vehicle_type` (case-insensitively).
+    pub fn type_capacity(mut self, vehicle_type: &str, passengers: f64) -> Self {
+        self.capacities
+            .insert(vehicle_type.trim().to_lowercase(), passengers);
+        self
+    }
+
+    pub fn load_in_percent(mut self) -> Self {
+        self.percent = true;
+        self
+    }
+
+    /// Load factors at which a vehicle stops being empty, has few seats, is standing room
+    /// only and is full, in that order.
+    pub fn thresholds(mut self, thresholds: [f64; 4]) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    pub fn capacity(&self, vehicle: &VehicleData) -> f64 {
+        if self.percent {
+            return 100.0;
+        }
+        vehicle
+            .r#type
+            .as_deref()
+            .and_then(|t| self.capacities.get(&t.trim().to_lowercase()))
+            .copied()
+            .unwrap_or(self.default_capacity)
+    }
+
+    /// `pax_load` relative to capacity, where 1.0 is full.
+    pub fn load_factor(&self, vehicle: &VehicleData) -> Option<f64> {
+        let load = vehicle.pax_load.filter(|l| l.is_finite() && *l >= 0.0)?;
+        let capacity = self.capacity(vehicle);
+        (capacity > 0.0).then(|| load / capacity)
+    }
+
+    pub fn classify(&self, vehicle: &VehicleData) -> Option<Occupancy> {
+        let factor = self.load_factor(vehicle)?;
+        let [empty, many, few, full] = self.thresholds;
+        Some(if factor >= full {
+            Occupancy::Full
+        } else if factor >= few {
+            Occupancy::StandingRoomOnly
+        } else if factor >= many {
+            Occupancy::FewSeatsAvailable
+        } else if factor >= empty {
+            Occupancy::ManySeatsAvailable
+        } else {
+            Occupancy::Empty
+        })
+    }
+
+    /// Per-route crowding for in-service vehicles, most loaded routes first.
+    pub fn by_route(&self, vehicles: &[VehicleData]) -> Vec<RouteOccupancy> {
+        let mut routes: HashMap<&str, RouteOccupancy> = HashMap::new();
+        for vehicle in vehicles {
+            if vehicle.out_of_service.unwrap_or(false) {
+                continue;
+            }
+            let Some(route_id) = vehicle.route_id.as_deref() else {
+                continue;
+            };
+            let entry = routes.entry(route_id).or_insert_with(|| RouteOccupancy {
+                route_id: route_id.to_string(),
+                vehicles: 0,
+                reporting: 0,
+                passengers: 0.0,
+                capacity: 0.0,
+                load_factor: None,
+                counts: HashMap::new(),
+                most_crowded: None,
+            });
+            entry.vehicles += 1;
+            let (Some(factor), Some(occupancy)) =
+                (self.load_factor(vehicle), self.classify(vehicle))
+            else {
+                continue;
+            };
+            let capacity = self.capacity(vehicle);
+            entry.reporting += 1;
+            entry.passengers += factor * capacity;
+            entry.capacity += capacity;
+            *entry.counts.entry(occupancy).or_default() += 1;
+            entry.most_crowded = entry.most_crowded.max(Some(occupancy));
+        }
+
+        let mut routes: Vec<RouteOccupancy> = routes
+            .into_values()
+            .map(|mut r| {
+                r.load_factor = (r.capacity > 0.0).then(|| r.passengers / r.capacity);
+                r
+            })
+            .collect();
+        routes.sort_by(|a, b| {
+            b.load_factor
+                .unwrap_or(-1.0)
+                .total_cmp(&a.load_factor.unwrap_or(-1.0))
+                .then_with(|| a.route_id.cmp(&b.route_id))
+        });
+        routes
+    }
+}
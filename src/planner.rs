@@ -0,0 +1,810 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use tokio::task::JoinSet;
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::geo::haversine_meters;
+use crate::route_stops::stops_for_route;
+use crate::transfers::{TransferGraph, TransferGraphBuilder};
+use crate::types::{Coordinate, ETAData, StopData};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Leg {
+    /// `None` stop ids stand for the coordinates the trip was planned from or to.
+    Walk {
+        from_stop_id: Option<String>,
+        to_stop_id: Option<String>,
+        meters: f64,
+        time: Duration,
+    },
+    Ride {
+        route_id: String,
+        board_stop_id: String,
+        alight_stop_id: String,
+        stop_count: usize,
+        wait: Duration,
+        /// Whether `wait` came from a live ETA rather than `default_wait`.
+        live_wait: bool,
+        ride: Duration,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Itinerary {
+    pub legs: Vec<Leg>,
+    pub transfers: usize,
+    pub duration: Duration,
+}
+
+impl Itinerary {
+    fn new(legs: Vec<Leg>, seconds: f64) -> Self {
+        let rides = legs
+            .iter()
+            .filter(|l| matches!(l, Leg::Ride { .. }))
+            .count();
+        Self {
+            legs,
+            transfers: rides.saturating_sub(1),
+            duration: Duration::from_secs_f64(seconds.max(0.0)),
+        }
+    }
+}
+
+impl ETAData {
+    /// Minutes until arrival parsed from the free-text `eta` ("5 min", "1 hr 10 min",
+    /// "less than 1 min"). Only numbers followed by a minute or hour unit count, so clock
+    /// times such as "10:35 AM" and text without a duration give `None`.
+    pub fn minutes_away(&self) -> Option<f64> {
+        if self.out_of_service {
+            return None;
+        }
+        let text = self.eta.to_lowercase();
+        if text.contains(':') {
+            return None;
+        }
+        if text.contains("less than") || text.starts_with('<') || text.contains("arriving") {
+            return Some(0.0);
+        }
+
+        let mut tokens: Vec<String> = Vec::new();
+        let mut previous_numeric = None;
+        for c in text.chars() {
+            let numeric = c.is_ascii_digit() || c == '.';
+            if !numeric && !c.is_alphabetic() {
+                previous_numeric = None;
+                continue;
+            }
+            match tokens.last_mut() {
+                Some(token) if previous_numeric == Some(numeric) => token.push(c),
+                _ => tokens.push(c.to_string()),
+            }
+            previous_numeric = Some(numeric);
+        }
+
+        let mut minutes = None;
+        for pair in tokens.windows(2) {
+            let Ok(n) = pair[0].parse::<f64>() else {
+                continue;
+            };
+            let unit = pair[1].as_str();
+            let factor = if unit == "m" || unit.starts_with("min") {
+                1.0
+            } else if unit == "h" || unit.starts_with("hr") || unit.starts_with("hour") {
+                60.0
+            } else {
+                continue;
+            };
+            *minutes.get_or_insert(0.0) += n * factor;
+        }
+        minutes
+    }
+}
+
+impl PassioGoClient {
+    /// Plans between two stops, then fetches live ETAs for the boardings it found and plans
+    /// again with them. Stops whose ETAs fail to load fall back to `default_wait`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn plan_trip(
+        &self,
+        system_id: i64,
+        from_stop_id: &str,
+        to_stop_id: &str,
+    ) -> Result<Vec<Itinerary>, ApiError> {
+        let stops = self.get_stops(system_id).await?;
+        let mut planner = TripPlanner::new(&stops);
+
+        let boardings: HashSet<(String, String)> = planner
+            .plan(from_stop_id, to_stop_id)
+            .into_iter()
+            .flat_map(|i| i.legs)
+            .filter_map(|leg| match leg {
+                Leg::Ride {
+                    route_id,
+                    board_stop_id,
+                    ..
+                } => Some((board_stop_id, route_id)),
+                Leg::Walk { .. } => None,
+            })
+            .collect();
+
+        let mut tasks = JoinSet::new();
+        for (stop_id, route_id) in boardings {
+            let Some(&position) = stops
+                .iter()
+                .find(|s| s.id == stop_id)
+                .and_then(|s| s.routes_and_positions.get(&route_id))
+                .and_then(|p| p.first())
+            else {
+                continue;
+            };
+            let client = self.clone();
+            tasks.spawn(async move {
+                let etas = client
+                    .get_etas(&stop_id, &route_id, &position, &system_id)
+                    .await;
+                (stop_id, etas)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((stop_id, Ok(etas))) = joined {
+                planner.add_etas(&stop_id, &etas);
+            }
+        }
+
+        Ok(planner.plan(from_stop_id, to_stop_id))
+    }
+}
+
+struct PlannedRoute {
+    route_id: String,
+    /// Indexes into the planner's stops, in travel order.
+    entries: Vec<usize>,
+    cumulative: Vec<f64>,
+    is_loop: bool,
+}
+
+impl PlannedRoute {
+    /// Stops reachable after boarding at `entry`, with hop count and distance ridden.
+    /// Loops can be ridden past their end and round again.
+    fn forward(&self, entry: usize) -> Vec<(usize, usize, f64)> {
+        let last = self.entries.len() - 1;
+        let mut reachable: Vec<(usize, usize, f64)> = (entry + 1..=last)
+            .map(|j| (j, j - entry, self.cumulative[j] - self.cumulative[entry]))
+            .collect();
+        if self.is_loop {
+            let to_end = self.cumulative[last] - self.cumulative[entry];
+            reachable.extend(
+                (1..entry.min(last)).map(|j| (j, last - entry + j, to_end + self.cumulative[j])),
+            );
+        }
+        reachable
+    }
+}
+
+#[derive(Clone)]
+struct Label {
+    seconds: f64,
+    legs: Vec<Leg>,
+}
+
+/// Finds itineraries of up to `max_transfers + 1` rides over the route/stop graph built
+/// from `routes_and_positions`, with walking transfers between nearby stops.
+///
+/// Vehicles are assumed to ride at `average_speed` along straight lines between stops, and
+/// each boarding waits for the next live ETA added with `add_etas`, or `default_wait`.
+pub struct TripPlanner<'a> {
+    stops: &'a [StopData],
+    stop_index: HashMap<&'a str, usize>,
+    routes: Vec<PlannedRoute>,
+    serving: Vec<Vec<(usize, usize)>>,
+    live_etas: HashMap<(String, String), Vec<f64>>,
+    transfers: TransferGraph,
+    max_transfers: usize,
+    max_walk: f64,
+    walking_speed: f64,
+    average_speed: f64,
+    default_wait: Duration,
+}
+
+impl<'a> TripPlanner<'a> {
+    pub fn new(stops: &'a [StopData]) -> Self {
+        let stop_index: HashMap<&str, usize> = stops
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.id.as_str(), i))
+            .collect();
+
+        let route_ids: HashSet<&str> = stops
+            .iter()
+            .flat_map(|s| s.routes_and_positions.keys())
+            .map(String::as_str)
+            .collect();
+        let mut route_ids: Vec<&str> = route_ids.into_iter().collect();
+        route_ids.sort_unstable();
+
+        let mut routes = Vec::new();
+        let mut serving = vec![Vec::new(); stops.len()];
+        for route_id in route_ids {
+            let ordered = stops_for_route(stops, route_id);
+            let entries: Vec<usize> = ordered
+                .stops
+                .iter()
+                .filter(|s| s.stop.latitude.is_some() && s.stop.longitude.is_some())
+                .filter_map(|s| stop_index.get(s.stop.id.as_str()).copied())
+                .collect();
+            if entries.len() < 2 {
+                continue;
+            }
+            let mut cumulative = vec![0.0];
+            for pair in entries.windows(2) {
+                let hop = stop_distance(&stops[pair[0]], &stops[pair[1]]).unwrap_or_default();
+                cumulative.push(cumulative.last().copied().unwrap_or_default() + hop);
+            }
+            for (entry, &stop) in entries.iter().enumerate() {
+                serving[stop].push((routes.len(), entry));
+            }
+            routes.push(PlannedRoute {
+                route_id: route_id.to_string(),
+                is_loop: ordered.is_loop(),
+                entries,
+                cumulative,
+            });
+        }
+
+        let mut planner = Self {
+            stops,
+            stop_index,
+            routes,
+            serving,
+            live_etas: HashMap::new(),
+            transfers: TransferGraph::default(),
+            max_transfers: 2,
+            max_walk: 400.0,
+            walking_speed: 1.3,
+            average_speed: 5.0,
+            default_wait: Duration::from_secs(5 * 60),
+        };
+        planner.build_transfers();
+        planner
+    }
+
+    pub fn max_transfers(mut self, transfers: usize) -> Self {
+        self.max_transfers = transfers;
+        self
+    }
+
+    /// Longest walk in meters, both for transfers and to or from the trip's endpoints.
+    pub fn max_walk(mut self, meters: f64) -> Self {
+        self.max_walk = meters;
+        self.build_transfers();
+        self
+    }
+
+    pub fn walking_speed(mut self, meters_per_second: f64) -> Self {
+        self.walking_speed = meters_per_second;
+        self.build_transfers();
+        self
+    }
+
+    /// Average vehicle speed in meters per second, dwell time included.
+    pub fn average_speed(mut self, meters_per_second: f64) -> Self {
+        self.average_speed = meters_per_second;
+        self
+    }
+
+    /// The walking transfers used between rides, built from `max_walk` and `walking_speed`.
+    pub fn transfers(&self) -> &TransferGraph {
+        &self.transfers
+    }
+
+    pub fn default_wait(mut self, wait: Duration) -> Self {
+        self.default_wait = wait;
+        self
+    }
+
+    /// Uses a stop's `get_etas` result as the wait for boarding each route there.
+    pub fn add_etas(&mut self, stop_id: &str, etas: &[ETAData]) {
+        for eta in etas {
+            if let Some(minutes) = eta.minutes_away() {
+                let waits = self
+                    .live_etas
+                    .entry((stop_id.to_string(), eta.route_id.clone()))
+                    .or_default();
+                waits.push(minutes * 60.0);
+                waits.sort_by(f64::total_cmp);
+            }
+        }
+    }
+
+    /// Stops within `max_meters` of a point, nearest first.
+    pub fn nearest_stops(&self, point: Coordinate, max_meters: f64) -> Vec<(&'a StopData, f64)> {
+        let mut near: Vec<(&StopData, f64)> = self
+            .stops
+            .iter()
+            .filter_map(|s| Some((s, distance_to(s, point)?)))
+            .filter(|(_, d)| *d <= max_meters)
+            .collect();
+        near.sort_by(|a, b| a.1.total_cmp(&b.1));
+        near
+    }
+
+    /// Itineraries between two stops, fastest first: the quickest for each number of
+    /// transfers that beats every option with fewer, plus a direct walk when the stops are
+    /// close enough.
+    pub fn plan(&self, from_stop_id: &str, to_stop_id: &str) -> Vec<Itinerary> {
+        let (Some(&from), Some(&to)) = (
+            self.stop_index.get(from_stop_id),
+            self.stop_index.get(to_stop_id),
+        ) else {
+            return Vec::new();
+        };
+
+        let mut starts = vec![(
+            from,
+            Label {
+                seconds: 0.0,
+                legs: Vec::new(),
+            },
+        )];
+        for (stop, meters) in self.walks_from(from) {
+            starts.push((
+                stop,
+                Label {
+                    seconds: meters / self.walking_speed,
+                    legs: vec![self.walk(Some(from), Some(stop), meters)],
+                },
+            ));
+        }
+        let mut ends = vec![(to, None)];
+        ends.extend(self.walks_from(to).into_iter().map(|(s, m)| (s, Some(m))));
+
+        let mut itineraries = self.search(starts, &ends, Some(to));
+        if let Some(meters) = stop_distance(&self.stops[from], &self.stops[to])
+            && meters <= self.max_walk
+            && from != to
+        {
+            itineraries.push(Itinerary::new(
+                vec![self.walk(Some(from), Some(to), meters)],
+                meters / self.walking_speed,
+            ));
+        }
+        sort_itineraries(itineraries)
+    }
+
+    /// Like `plan`, between the stops within `max_walk` of each point (or the nearest stop
+    /// when none are), including the walks to and from them.
+    pub fn plan_between(&self, from: Coordinate, to: Coordinate) -> Vec<Itinerary> {
+        let starts: Vec<(usize, Label)> = self
+            .endpoint_stops(from)
+            .into_iter()
+            .map(|(stop, meters)| {
+                (
+                    stop,
+                    Label {
+                        seconds: meters / self.walking_speed,
+                        legs: vec![self.walk(None, Some(stop), meters)],
+                    },
+                )
+            })
+            .collect();
+        let ends: Vec<(usize, Option<f64>)> = self
+            .endpoint_stops(to)
+            .into_iter()
+            .map(|(stop, meters)| (stop, Some(meters)))
+            .collect();
+
+        let mut itineraries = self.search(starts, &ends, None);
+        let meters = from.distance_meters(&to);
+        if meters <= self.max_walk {
+            itineraries.push(Itinerary::new(
+                vec![self.walk(None, None, meters)],
+                meters / self.walking_speed,
+            ));
+        }
+        sort_itineraries(itineraries)
+    }
+
+    // Round-based search: round `k` finds the best arrival at every stop using `k + 1`
+    // rides, only keeping stops it reached sooner than any earlier round.
+    fn search(
+        &self,
+        starts: Vec<(usize, Label)>,
+        ends: &[(usize, Option<f64>)],
+        destination: Option<usize>,
+    ) -> Vec<Itinerary> {
+        let mut best: HashMap<usize, f64> = HashMap::new();
+        let mut reached: HashMap<usize, Label> = HashMap::new();
+        for (stop, label) in starts {
+            if best.get(&stop).is_none_or(|b| label.seconds < *b) {
+                best.insert(stop, label.seconds);
+                reached.insert(stop, label);
+            }
+        }
+
+        let mut itineraries = Vec::new();
+        let mut best_total = f64::INFINITY;
+        for _ in 0..=self.max_transfers {
+            let mut next: HashMap<usize, Label> = HashMap::new();
+            for (&stop, label) in &reached {
+                for &(route_index, entry) in &self.serving[stop] {
+                    let route = &self.routes[route_index];
+                    let (wait, live_wait) = self.wait_at(stop, &route.route_id, label.seconds);
+                    for (j, stop_count, meters) in route.forward(entry) {
+                        let alight = route.entries[j];
+                        let ride = meters / self.average_speed.max(0.1);
+                        let seconds = label.seconds + wait + ride;
+                        if alight == stop || best.get(&alight).is_some_and(|b| seconds >= *b) {
+                            continue;
+                        }
+                        best.insert(alight, seconds);
+                        let mut legs = label.legs.clone();
+                        legs.push(Leg::Ride {
+                            route_id: route.route_id.clone(),
+                            board_stop_id: self.stops[stop].id.clone(),
+                            alight_stop_id: self.stops[alight].id.clone(),
+                            stop_count,
+                            wait: Duration::from_secs_f64(wait),
+                            live_wait,
+                            ride: Duration::from_secs_f64(ride),
+                        });
+                        next.insert(alight, Label { seconds, legs });
+                    }
+                }
+            }
+
+            let mut walked = Vec::new();
+            for (&stop, label) in &next {
+                for (neighbour, meters) in self.walks_from(stop) {
+                    let seconds = label.seconds + meters / self.walking_speed;
+                    if best.get(&neighbour).is_some_and(|b| seconds >= *b) {
+                        continue;
+                    }
+                    best.insert(neighbour, seconds);
+                    let mut legs = label.legs.clone();
+                    legs.push(self.walk(Some(stop), Some(neighbour), meters));
+                    walked.push((neighbour, Label { seconds, legs }));
+                }
+            }
+            for (stop, label) in walked {
+                if best.get(&stop) == Some(&label.seconds) {
+                    next.insert(stop, label);
+                }
+            }
+
+            let arrival = ends
+                .iter()
+                .filter_map(|&(stop, walk)| {
+                    let label = next.get(&stop)?;
+                    let walk_seconds = walk.map_or(0.0, |m| m / self.walking_speed);
+                    Some((label, stop, walk, label.seconds + walk_seconds))
+                })
+                .min_by(|a, b| a.3.total_cmp(&b.3));
+            if let Some((label, stop, walk, seconds)) = arrival
+                && seconds < best_total
+            {
+                best_total = seconds;
+                let mut legs = label.legs.clone();
+                if let Some(meters) = walk {
+                    legs.push(self.walk(Some(stop), destination, meters));
+                }
+                itineraries.push(Itinerary::new(legs, seconds));
+            }
+
+            if next.is_empty() {
+                break;
+            }
+            reached = next;
+        }
+        itineraries
+    }
+
+    fn wait_at(&self, stop: usize, route_id: &str, arrival_seconds: f64) -> (f64, bool) {
+        self.live_etas
+            .get(&(self.stops[stop].id.clone(), route_id.to_string()))
+            .and_then(|etas| etas.iter().find(|eta| **eta >= arrival_seconds))
+            .map_or((self.default_wait.as_secs_f64(), false), |eta| {
+                (eta - arrival_seconds, true)
+            })
+    }
+
+    fn walks_from(&self, stop: usize) -> Vec<(usize, f64)> {
+        self.transfers
+            .from_stop(&self.stops[stop].id)
+            .iter()
+            .filter_map(|t| Some((*self.stop_index.get(t.to_stop_id.as_str())?, t.meters)))
+            .collect()
+    }
+
+    fn build_transfers(&mut self) {
+        self.transfers = TransferGraphBuilder::new()
+            .max_distance(self.max_walk)
+            .walking_speed(self.walking_speed)
+            .include_same_route(true)
+            .build(self.stops);
+    }
+
+    fn endpoint_stops(&self, point: Coordinate) -> Vec<(usize, f64)> {
+        let mut near: Vec<(usize, f64)> = self
+            .stops
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| Some((i, distance_to(s, point)?)))
+            .collect();
+        near.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let within = near.iter().filter(|(_, d)| *d <= self.max_walk).count();
+        near.truncate(within.max(1));
+        near
+    }
+
+    fn walk(&self, from: Option<usize>, to: Option<usize>, meters: f64) -> Leg {
+        Leg::Walk {
+            from_stop_id: from.map(|i| self.stops[i].id.clone()),
+            to_stop_id: to.map(|i| self.stops[i].id.clone()),
+            meters,
+            time: Duration::from_secs_f64(meters / self.walking_speed.max(0.1)),
+        }
+    }
+}
+
+fn sort_itineraries(mut itineraries: Vec<Itinerary>) -> Vec<Itinerary> {
+    itineraries.sort_by(|a, b| {
+        a.duration
+            .cmp(&b.duration)
+            .then(a.transfers.cmp(&b.transfers))
+    });
+    itineraries
+}
+
+fn distance_to(stop: &StopData, point: Coordinate) -> Option<f64> {
+    Some(haversine_meters(
+        stop.latitude?,
+        stop.longitude?,
+        point.latitude,
+        point.longitude,
+    ))
+}
+
+fn stop_distance(a: &StopData, b: &StopData) -> Option<f64> {
+    distance_to(
+        b,
+        Coordinate {
+            latitude: a.latitude?,
+            longitude: a.longitude?,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eta(text: &str) -> ETAData {
+        ETAData {
+            eta: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn at(north: f64, east: f64) -> Coordinate {
+        Coordinate {
+            latitude: 41.0 + north / 111_195.0,
+            longitude: -87.0 + east / (111_195.0 * 41f64.to_radians().cos()),
+        }
+    }
+
+    fn stop(id: &str, north: f64, east: f64, routes: &[(&str, &[f64])]) -> StopData {
+        let c = at(north, east);
+        StopData {
+            id: id.to_string(),
+            latitude: Some(c.latitude),
+            longitude: Some(c.longitude),
+            routes_and_positions: routes
+                .iter()
+                .map(|(r, p)| (r.to_string(), p.to_vec()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    // R1 runs S1 → S2 → S3, R2 runs S3 → S4 and R3 runs S5 → S6, with S4 and S5 100 m
+    // apart. R4 is a slow direct route from S1 to S4 by way of S7, and L loops round
+    // L1 → L2 → L3 → L1.
+    fn network() -> Vec<StopData> {
+        vec![
+            stop("S1", 0.0, 0.0, &[("R1", &[0.0]), ("R4", &[0.0])]),
+            stop("S2", 0.0, 1000.0, &[("R1", &[1.0])]),
+            stop("S3", 0.0, 2000.0, &[("R1", &[2.0]), ("R2", &[0.0])]),
+            stop("S4", 1000.0, 2000.0, &[("R2", &[1.0]), ("R4", &[2.0])]),
+            stop("S5", 1000.0, 2100.0, &[("R3", &[0.0])]),
+            stop("S6", 2000.0, 2100.0, &[("R3", &[1.0])]),
+            stop("S7", 3000.0, 0.0, &[("R4", &[1.0])]),
+            stop("L1", 5000.0, 0.0, &[("L", &[0.0, 3.0])]),
+            stop("L2", 5000.0, 1000.0, &[("L", &[1.0])]),
+            stop("L3", 6000.0, 1000.0, &[("L", &[2.0])]),
+        ]
+    }
+
+    fn planner(stops: &[StopData]) -> TripPlanner<'_> {
+        TripPlanner::new(stops)
+            .average_speed(10.0)
+            .walking_speed(1.0)
+            .max_walk(150.0)
+            .default_wait(Duration::from_secs(60))
+    }
+
+    // Each leg as `route:board>alight` or `walk:from>to`, with the total in seconds.
+    fn summary(itinerary: &Itinerary) -> (Vec<String>, u64) {
+        let legs = itinerary
+            .legs
+            .iter()
+            .map(|leg| match leg {
+                Leg::Ride {
+                    route_id,
+                    board_stop_id,
+                    alight_stop_id,
+                    ..
+                } => format!("{}:{}>{}", route_id, board_stop_id, alight_stop_id),
+                Leg::Walk {
+                    from_stop_id,
+                    to_stop_id,
+                    ..
+                } => format!(
+                    "walk:{}>{}",
+                    from_stop_id.as_deref().unwrap_or("here"),
+                    to_stop_id.as_deref().unwrap_or("there")
+                ),
+            })
+            .collect();
+        (legs, itinerary.duration.as_secs_f64().round() as u64)
+    }
+
+    fn summaries(itineraries: &[Itinerary]) -> Vec<(Vec<String>, u64)> {
+        itineraries.iter().map(summary).collect()
+    }
+
+    fn legs(legs: &[&str], seconds: u64) -> (Vec<String>, u64) {
+        (legs.iter().map(|l| l.to_string()).collect(), seconds)
+    }
+
+    #[test]
+    fn plans_direct_rides_and_transfers() {
+        let stops = network();
+        let planner = planner(&stops);
+
+        // 60 s wait, then 1 km at 10 m/s.
+        assert_eq!(
+            summaries(&planner.plan("S1", "S2")),
+            [legs(&["R1:S1>S2"], 160)]
+        );
+
+        // One transfer beats the slow direct R4, and both are offered.
+        let itineraries = planner.plan("S1", "S4");
+        assert_eq!(
+            summaries(&itineraries),
+            [
+                legs(&["R1:S1>S3", "R2:S3>S4"], 420),
+                legs(&["R4:S1>S4"], 643),
+            ]
+        );
+        assert_eq!(itineraries[0].transfers, 1);
+        assert_eq!(itineraries[1].transfers, 0);
+
+        // Two transfers with a walk between S4 and S5 beat R4 and the same walk.
+        let itineraries = planner.plan("S1", "S6");
+        assert_eq!(
+            summaries(&itineraries),
+            [
+                legs(&["R1:S1>S3", "R2:S3>S4", "walk:S4>S5", "R3:S5>S6"], 680),
+                legs(&["R4:S1>S4", "walk:S4>S5", "R3:S5>S6"], 903),
+            ]
+        );
+        assert_eq!(itineraries[0].transfers, 2);
+        assert!(matches!(
+            itineraries[0].legs[0],
+            Leg::Ride {
+                stop_count: 2,
+                live_wait: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn respects_max_transfers() {
+        let stops = network();
+        assert_eq!(
+            summaries(&planner(&stops).max_transfers(1).plan("S1", "S6")),
+            [legs(&["R4:S1>S4", "walk:S4>S5", "R3:S5>S6"], 903)]
+        );
+        assert!(planner(&stops).max_transfers(0).plan("S1", "S6").is_empty());
+        assert_eq!(
+            summaries(&planner(&stops).max_transfers(0).plan("S1", "S4")),
+            [legs(&["R4:S1>S4"], 643)]
+        );
+        assert!(planner(&stops).plan("S1", "nowhere").is_empty());
+    }
+
+    #[test]
+    fn walks_between_nearby_stops() {
+        let stops = network();
+        assert_eq!(
+            summaries(&planner(&stops).plan("S4", "S5")),
+            [legs(&["walk:S4>S5"], 100)]
+        );
+    }
+
+    #[test]
+    fn loops_are_ridden_past_their_end() {
+        let stops = network();
+        let planner = planner(&stops);
+        let loop_route = planner.routes.iter().find(|r| r.route_id == "L").unwrap();
+        assert!(loop_route.is_loop);
+        let hops: Vec<(usize, usize)> = loop_route
+            .forward(2)
+            .iter()
+            .map(|&(j, count, _)| (j, count))
+            .collect();
+        assert_eq!(hops, [(3, 1), (1, 2)]);
+
+        // L3 back round through L1 to L2: about 1414 m and 1000 m.
+        let itineraries = planner.plan("L3", "L2");
+        assert_eq!(summaries(&itineraries), [legs(&["L:L3>L2"], 301)]);
+        assert!(matches!(
+            itineraries[0].legs[0],
+            Leg::Ride { stop_count: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn live_etas_replace_the_default_wait() {
+        let stops = network();
+        let mut planner = planner(&stops);
+        planner.add_etas(
+            "S1",
+            &[ETAData {
+                route_id: "R1".to_string(),
+                ..eta("2 min")
+            }],
+        );
+        let itineraries = planner.plan("S1", "S2");
+        assert_eq!(summaries(&itineraries), [legs(&["R1:S1>S2"], 220)]);
+        assert!(matches!(
+            itineraries[0].legs[0],
+            Leg::Ride {
+                live_wait: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn plans_between_coordinates() {
+        let stops = network();
+        let itineraries = planner(&stops).plan_between(at(0.0, -50.0), at(0.0, 1050.0));
+        assert_eq!(
+            summaries(&itineraries),
+            [legs(&["walk:here>S1", "R1:S1>S2", "walk:S2>there"], 260)]
+        );
+
+        // Close enough to just walk.
+        assert_eq!(
+            summaries(&planner(&stops).plan_between(at(0.0, 10.0), at(0.0, 110.0))),
+            [legs(&["walk:here>there"], 100)]
+        );
+    }
+
+    #[test]
+    fn minutes_away_reads_durations_only() {
+        assert_eq!(eta("5 min").minutes_away(), Some(5.0));
+        assert_eq!(eta("1 hr 10 min").minutes_away(), Some(70.0));
+        assert_eq!(eta("2 hours").minutes_away(), Some(120.0));
+        assert_eq!(eta("less than 1 min").minutes_away(), Some(0.0));
+        assert_eq!(eta("<1 min").minutes_away(), Some(0.0));
+        assert_eq!(eta("10:35 AM").minutes_away(), None);
+        assert_eq!(eta("10:35").minutes_away(), None);
+        assert_eq!(eta("Bus 12").minutes_away(), None);
+        assert_eq!(eta("").minutes_away(), None);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::types::StopData;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RouteStop<'a> {
+    pub position: f64,
+    pub stop: &'a StopData,
+}
+
+/// The stops of one route in travel order, one entry per position, so a stop served twice
+/// (such as a loop's start and end) appears twice.
+#[derive(Debug, Clone)]
+pub struct RouteStops<'a> {
+    pub route_id: String,
+    pub stops: Vec<RouteStop<'a>>,
+}
+
+impl<'a> RouteStops<'a> {
+    pub fn first(&self) -> Option<&'a StopData> {
+        self.stops.first().map(|s| s.stop)
+    }
+
+    pub fn last(&self) -> Option<&'a StopData> {
+        self.stops.last().map(|s| s.stop)
+    }
+
+    /// Whether the route ends back at the stop it started from.
+    pub fn is_loop(&self) -> bool {
+        self.stops.len() > 2 && self.first().map(|s| &s.id) == self.last().map(|s| &s.id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.stops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty()
+    }
+
+    /// Consecutive stop pairs in the direction of travel.
+    pub fn segments(&self) -> impl Iterator<Item = (RouteStop<'a>, RouteStop<'a>)> + '_ {
+        self.stops.windows(2).map(|pair| (pair[0], pair[1]))
+    }
+
+    /// Positions at which `stop_id` is served on this route.
+    pub fn positions_of(&self, stop_id: &str) -> Vec<f64> {
+        self.stops
+            .iter()
+            .filter(|s| s.stop.id == stop_id)
+            .map(|s| s.position)
+            .collect()
+    }
+}
+
+/// Collects the stops serving `route_id`, sorted by their `routes_and_positions` position.
+pub fn stops_for_route<'a>(stops: &'a [StopData], route_id: &str) -> RouteStops<'a> {
+    let mut ordered: Vec<RouteStop<'a>> = stops
+        .iter()
+        .flat_map(|stop| {
+            stop.routes_and_positions
+                .get(route_id)
+                .into_iter()
+                .flatten()
+                .map(move |&position| RouteStop { position, stop })
+        })
+        .collect();
+    ordered.sort_by(|a, b| a.position.total_cmp(&b.position));
+    RouteStops {
+        route_id: route_id.to_string(),
+        stops: ordered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(id: &str, positions: &[(&str, &[f64])]) -> StopData {
+        StopData {
+            id: id.to_string(),
+            routes_and_positions: positions
+                .iter()
+                .map(|(route, p)| (route.to_string(), p.to_vec()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(route: &RouteStops) -> Vec<String> {
+        route.stops.iter().map(|s| s.stop.id.clone()).collect()
+    }
+
+    #[test]
+    fn orders_stops_by_position() {
+        let stops = [
+            stop("C", &[("1", &[2.0])]),
+            stop("A", &[("1", &[0.0]), ("2", &[1.0])]),
+            stop("B", &[("1", &[1.0]), ("2", &[0.0])]),
+            stop("D", &[("2", &[2.0])]),
+        ];
+        let route = stops_for_route(&stops, "1");
+        assert_eq!(ids(&route), ["A", "B", "C"]);
+        assert!(!route.is_loop());
+        assert_eq!(route.first().map(|s| s.id.as_str()), Some("A"));
+        assert_eq!(route.last().map(|s| s.id.as_str()), Some("C"));
+        let segments: Vec<(&str, &str)> = route
+            .segments()
+            .map(|(a, b)| (a.stop.id.as_str(), b.stop.id.as_str()))
+            .collect();
+        assert_eq!(segments, [("A", "B"), ("B", "C")]);
+
+        assert_eq!(ids(&stops_for_route(&stops, "2")), ["B", "A", "D"]);
+        assert!(stops_for_route(&stops, "3").is_empty());
+    }
+
+    #[test]
+    fn loops_serve_their_first_stop_twice() {
+        let stops = [
+            stop("A", &[("1", &[0.0, 3.0])]),
+            stop("B", &[("1", &[1.0])]),
+            stop("C", &[("1", &[2.0])]),
+        ];
+        let route = stops_for_route(&stops, "1");
+        assert_eq!(ids(&route), ["A", "B", "C", "A"]);
+        assert!(route.is_loop());
+        assert_eq!(route.len(), 4);
+        assert_eq!(route.positions_of("A"), [0.0, 3.0]);
+        assert_eq!(route.positions_of("B"), [1.0]);
+        assert_eq!(route.segments().count(), 3);
+
+        // Out and straight back is not a loop.
+        let there_and_back = [stop("A", &[("1", &[0.0, 1.0])])];
+        assert!(!stops_for_route(&there_and_back, "1").is_loop());
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use chrono::{NaiveTime, Timelike};
+
+use crate::helpers::parse_clock_time;
+use crate::types::ETAData;
+
+const DAY_SECONDS: i64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleComparison {
+    pub scheduled: NaiveTime,
+    pub predicted: NaiveTime,
+    /// Positive when the vehicle is running behind its schedule.
+    pub delay_seconds: i64,
+}
+
+impl ScheduleComparison {
+    pub fn is_late(&self, tolerance: Duration) -> bool {
+        self.delay_seconds > tolerance.as_secs() as i64
+    }
+
+    pub fn is_early(&self, tolerance: Duration) -> bool {
+        -self.delay_seconds > tolerance.as_secs() as i64
+    }
+}
+
+impl ETAData {
+    /// The scheduled arrival from `schedule_time`, or the first of `schedule_times`.
+    pub fn scheduled_time(&self) -> Option<NaiveTime> {
+        self.schedule_time
+            .iter()
+            .chain(self.schedule_times.iter().flatten())
+            .find_map(|t| parse_clock_time(t.trim_matches('"')))
+            .and_then(|seconds| {
+                NaiveTime::from_num_seconds_from_midnight_opt(seconds % DAY_SECONDS as u32, 0)
+            })
+    }
+
+    /// Compares the predicted arrival (`now` plus `eta`) against the scheduled time. `now`
+    /// is the local time in the route's timezone; differences wrap at midnight, so the
+    /// delay is always within twelve hours either way.
+    pub fn compare_to_schedule(&self, now: NaiveTime) -> Option<ScheduleComparison> {
+        let scheduled = self.scheduled_time()?;
+        let minutes = self.minutes_away()?;
+        let predicted_seconds = (now.num_seconds_from_midnight() as i64
+            + (minutes * 60.0).round() as i64)
+            .rem_euclid(DAY_SECONDS);
+        let predicted = NaiveTime::from_num_seconds_from_midnight_opt(predicted_seconds as u32, 0)?;
+
+        let mut delay_seconds = (predicted_seconds - scheduled.num_seconds_from_midnight() as i64)
+            .rem_euclid(DAY_SECONDS);
+        if delay_seconds > DAY_SECONDS / 2 {
+            delay_seconds -= DAY_SECONDS;
+        }
+        Some(ScheduleComparison {
+            scheduled,
+            predicted,
+            delay_seconds,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eta(text: &str, scheduled: &str) -> ETAData {
+        ETAData {
+            eta: text.to_string(),
+            schedule_time: Some(scheduled.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn scheduled_time_falls_back_to_schedule_times() {
+        let quoted = ETAData {
+            schedule_times: Some(vec!["\"10:05 PM\"".to_string(), "10:35 PM".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(quoted.scheduled_time(), Some(at(22, 5)));
+        assert_eq!(eta("5 min", "7:30 AM").scheduled_time(), Some(at(7, 30)));
+        assert_eq!(eta("5 min", "soon").scheduled_time(), None);
+        assert_eq!(ETAData::default().scheduled_time(), None);
+    }
+
+    #[test]
+    fn compares_predicted_arrival_to_schedule() {
+        let late = eta("10 min", "10:05 AM")
+            .compare_to_schedule(at(10, 0))
+            .unwrap();
+        assert_eq!(late.predicted, at(10, 10));
+        assert_eq!(late.delay_seconds, 300);
+        assert!(late.is_late(Duration::from_secs(120)));
+        assert!(!late.is_late(Duration::from_secs(300)));
+        assert!(!late.is_early(Duration::ZERO));
+
+        let early = eta("2 min", "10:05 AM")
+            .compare_to_schedule(at(10, 0))
+            .unwrap();
+        assert_eq!(early.delay_seconds, -180);
+        assert!(early.is_early(Duration::from_secs(60)));
+
+        assert_eq!(
+            eta("10:35 AM", "10:05 AM").compare_to_schedule(at(10, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn delay_wraps_at_midnight() {
+        // Predicted 00:05 against 23:55 is ten minutes late, not 23h50m early.
+        let late = eta("10 min", "11:55 PM")
+            .compare_to_schedule(at(23, 55))
+            .unwrap();
+        assert_eq!(late.predicted, at(0, 5));
+        assert_eq!(late.delay_seconds, 600);
+
+        let early = eta("5 min", "12:10 AM")
+            .compare_to_schedule(at(23, 55))
+            .unwrap();
+        assert_eq!(early.delay_seconds, -600);
+
+        // Differences past twelve hours are read the other way around the clock.
+        let wrapped = eta("1 min", "9:00 PM")
+            .compare_to_schedule(at(8, 59))
+            .unwrap();
+        assert_eq!(wrapped.delay_seconds, 12 * 3600);
+        let wrapped = eta("2 min", "9:00 PM")
+            .compare_to_schedule(at(8, 59))
+            .unwrap();
+        assert_eq!(wrapped.delay_seconds, -(12 * 3600 - 60));
+    }
+}
@@ -0,0 +1,272 @@
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::geo::GeoFilter;
+use crate::types::TransportationSystemData;
+
+/// How many systems' routes `find_systems_in` fetches at once.
+const AREA_LOOKUPS_IN_FLIGHT: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct SystemMatch {
+    pub system: TransportationSystemData,
+    pub score: f64,
+}
+
+impl PassioGoClient {
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn find_systems(&self, query: &str) -> Result<Vec<SystemMatch>, ApiError> {
+        let systems = self.get_systems().await?;
+        Ok(rank_systems(&systems, query))
+    }
+
+    /// Like `find_systems`, but only keeps systems with at least one route inside `area`.
+    /// Every candidate's routes are fetched, a few at a time, so prefer a specific query
+    /// over an empty one. Systems whose routes cannot be fetched are logged and left out.
+    #[tracing::instrument(level = "debug", skip(self, area))]
+    pub async fn find_systems_in(
+        &self,
+        query: &str,
+        area: GeoFilter,
+    ) -> Result<Vec<SystemMatch>, ApiError> {
+        let matches = self.find_systems(query).await?;
+
+        let mut queued = matches.iter().enumerate();
+        let mut tasks = JoinSet::new();
+        let mut keep = vec![false; matches.len()];
+        loop {
+            while tasks.len() < AREA_LOOKUPS_IN_FLIGHT
+                && let Some((idx, m)) = queued.next()
+            {
+                let client = self.clone();
+                let system_id = m.system.id;
+                tasks.spawn(async move { (idx, system_id, client.get_routes(system_id).await) });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            match joined {
+                Ok((idx, _, Ok(routes))) => {
+                    keep[idx] = routes.iter().any(|r| match (r.latitude, r.longitude) {
+                        (Some(lat), Some(lon)) => area.contains(lat, lon),
+                        _ => false,
+                    });
+                }
+                Ok((_, system_id, Err(e))) => {
+                    warn!(system_id, error = %e, "skipping system whose routes could not be fetched");
+                }
+                Err(_) => {}
+            }
+        }
+
+        Ok(matches
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(m, keep)| keep.then_some(m))
+            .collect())
+    }
+}
+
+/// Scores every system against `query` on its name, username and agency name, best first.
+/// Systems that do not match at all are dropped; an empty query keeps everything with a
+/// score of zero.
+pub fn rank_systems(systems: &[TransportationSystemData], query: &str) -> Vec<SystemMatch> {
+    let query = normalize(query);
+    let mut matches: Vec<SystemMatch> = systems
+        .iter()
+        .filter_map(|system| {
+            let score = if query.is_empty() {
+                0.0
+            } else {
+                [
+                    (&system.name, 1.0),
+                    (&system.username, 0.9),
+                    (&system.go_agency_name, 0.8),
+                ]
+                .into_iter()
+                .filter_map(|(field, weight)| {
+                    field.as_deref().map(|f| field_score(f, &query) * weight)
+                })
+                .fold(0.0, f64::max)
+            };
+            (query.is_empty() || score > 0.0).then(|| SystemMatch {
+                system: system.clone(),
+                score,
+            })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.system.name.cmp(&b.system.name))
+    });
+    matches
+}
+
+fn normalize(s: &str) -> String {
+    let mut lowered = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            lowered.extend(c.to_lowercase());
+        } else {
+            lowered.push(' ');
+        }
+    }
+    lowered.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn field_score(field: &str, query: &str) -> f64 {
+    let field = normalize(field);
+    if field.is_empty() {
+        return 0.0;
+    }
+    if field == query {
+        return 1.0;
+    }
+    if field.starts_with(query) {
+        return 0.9;
+    }
+    if field.split(' ').any(|w| w.starts_with(query)) {
+        return 0.8;
+    }
+    if field.contains(query) {
+        return 0.7;
+    }
+
+    // Fall back to per-word similarity so typos ("chicgo") and reordered words still match.
+    let words: Vec<&str> = field.split(' ').collect();
+    let tokens: Vec<&str> = query.split(' ').collect();
+    let total: f64 = tokens
+        .iter()
+        .map(|t| {
+            words
+                .iter()
+                .map(|w| word_similarity(t, w))
+                .fold(0.0, f64::max)
+        })
+        .sum();
+    let average = total / tokens.len() as f64;
+    if average >= 0.75 { average * 0.6 } else { 0.0 }
+}
+
+fn word_similarity(token: &str, word: &str) -> f64 {
+    if word.starts_with(token) {
+        return 1.0;
+    }
+    let a: Vec<char> = token.chars().collect();
+    let b: Vec<char> = word.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / longest as f64
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(
+        id: i64,
+        name: &str,
+        username: &str,
+        agency: Option<&str>,
+    ) -> TransportationSystemData {
+        TransportationSystemData {
+            id,
+            name: Some(name.to_string()),
+            username: Some(username.to_string()),
+            go_agency_name: agency.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn levenshtein_distances() {
+        assert_eq!(levenshtein(&chars("chicago"), &chars("chicago")), 0);
+        assert_eq!(levenshtein(&chars("chicgo"), &chars("chicago")), 1);
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+        assert_eq!(levenshtein(&chars("école"), &chars("ecole")), 1);
+    }
+
+    #[test]
+    fn normalize_folds_case_and_punctuation() {
+        assert_eq!(
+            normalize("  Université de  MONTRÉAL! "),
+            "université de montréal"
+        );
+        assert_eq!(normalize("École-Polytechnique"), "école polytechnique");
+        assert_eq!(normalize("ΣΤΆΣΗ"), "στάση");
+        assert_eq!(normalize("--"), "");
+    }
+
+    #[test]
+    fn field_scores_by_kind_of_match() {
+        assert_eq!(field_score("Chicago", "chicago"), 1.0);
+        assert_eq!(field_score("Chicago Transit", "chic"), 0.9);
+        assert_eq!(field_score("University of Chicago", "chic"), 0.8);
+        assert_eq!(field_score("UChicago", "chicago"), 0.7);
+        assert_eq!(field_score("École Polytechnique", "école"), 0.9);
+
+        let typo = field_score("University of Chicago", "chicgo");
+        assert!(typo > 0.0 && typo < 0.6, "{}", typo);
+        assert_eq!(field_score("University of Chicago", "boston"), 0.0);
+        assert_eq!(field_score("", "chicago"), 0.0);
+    }
+
+    #[test]
+    fn ranks_systems_best_first() {
+        let systems = [
+            system(1, "Loyola University Chicago", "loyola", None),
+            system(
+                2,
+                "University of Chicago",
+                "uchicago",
+                Some("UChicago Transit"),
+            ),
+            system(3, "Chicago", "chicagoshuttle", None),
+            system(4, "Boston University", "bu", None),
+        ];
+
+        let ids = |query: &str| -> Vec<i64> {
+            rank_systems(&systems, query)
+                .iter()
+                .map(|m| m.system.id)
+                .collect()
+        };
+        assert_eq!(ids("chicago"), [3, 1, 2]);
+        assert_eq!(ids("uchicago"), [2, 3, 1]);
+        assert_eq!(ids("CHICGO"), [3, 1, 2]);
+        assert_eq!(ids("nowhere"), Vec::<i64>::new());
+
+        // An empty query keeps everything, sorted by name.
+        let all = rank_systems(&systems, " ");
+        assert!(all.iter().all(|m| m.score == 0.0));
+        assert_eq!(
+            all.iter().map(|m| m.system.id).collect::<Vec<_>>(),
+            [4, 3, 1, 2]
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use chrono::{DateTime, Datelike, Days, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::helpers::parse_clock_time;
+use crate::types::RouteData;
+
+const DAY_SECONDS: u32 = 24 * 3600;
+
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Service on `days` from `start` until `end`. Overnight windows end on the following day;
+/// a window whose `end` equals its `start` runs all day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceWindow {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub overnight: bool,
+}
+
+impl ServiceWindow {
+    fn covers(&self, day: Weekday, time: NaiveTime) -> bool {
+        let all_day = self.start == self.end;
+        let today = self.days.contains(&day)
+            && (all_day || (time >= self.start && (self.overnight || time < self.end)));
+        let carried = self.overnight && self.days.contains(&day.pred()) && time < self.end;
+        today || carried
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceHours {
+    pub windows: Vec<ServiceWindow>,
+    pub timezone: Tz,
+}
+
+impl ServiceHours {
+    /// Parses free-form hours such as `Mon-Fri 7:00am - 11:00pm; Sat & Sun 10am-2am`,
+    /// `Weekdays 7-9am, 4-6pm` or `24/7`. Time ranges without days apply every day.
+    /// Returns `None` when no time range is recognised.
+    pub fn parse(text: &str, timezone: Tz) -> Option<Self> {
+        let lower = text.to_lowercase();
+        if lower.contains("24/7") || lower.contains("24 hours") {
+            return Some(Self {
+                windows: vec![ServiceWindow {
+                    days: WEEK.to_vec(),
+                    start: NaiveTime::MIN,
+                    end: NaiveTime::MIN,
+                    overnight: false,
+                }],
+                timezone,
+            });
+        }
+
+        // Days may lead their time ranges (`Mon-Fri 7am-7pm`) or trail them
+        // (`7am-7pm Monday through Friday`); ranges with no days at all run every day.
+        let tokens = tokenize(&lower);
+        let mut windows: Vec<ServiceWindow> = Vec::new();
+        let mut leading: Vec<Weekday> = Vec::new();
+        let mut pending: Vec<(NaiveTime, NaiveTime)> = Vec::new();
+        let mut last_was_days = false;
+        let mut i = 0;
+        while i < tokens.len() {
+            if let Some(named) = day_at(&tokens, i) {
+                let range_end = match tokens.get(i + 1) {
+                    Some(dash) if dash == "-" => day_at(&tokens, i + 2)
+                        .filter(|d| d.len() == 1 && named.len() == 1)
+                        .map(|d| d[0]),
+                    _ => None,
+                };
+                let mut days = Vec::new();
+                if let Some(last) = range_end {
+                    let mut day = named[0];
+                    days.push(day);
+                    while day != last {
+                        day = day.succ();
+                        days.push(day);
+                    }
+                    i += 3;
+                } else {
+                    days.extend(named);
+                    i += 1;
+                }
+
+                if !pending.is_empty() {
+                    for (start, end) in pending.drain(..) {
+                        add_window(&mut windows, days.clone(), start, end);
+                    }
+                    leading.clear();
+                } else if last_was_days {
+                    leading.extend(days);
+                } else {
+                    leading = days;
+                }
+                last_was_days = true;
+                continue;
+            }
+
+            if let (Some(start), Some(dash), Some(end)) = (
+                clock(&tokens[i]),
+                tokens.get(i + 1),
+                tokens.get(i + 2).and_then(|t| clock(t)),
+            ) && dash == "-"
+            {
+                let (start, end) = resolve_meridiem(start, end);
+                let start_time =
+                    NaiveTime::from_num_seconds_from_midnight_opt(start % DAY_SECONDS, 0)?;
+                let end_time = NaiveTime::from_num_seconds_from_midnight_opt(end % DAY_SECONDS, 0)?;
+                if leading.is_empty() {
+                    pending.push((start_time, end_time));
+                } else {
+                    add_window(&mut windows, leading.clone(), start_time, end_time);
+                }
+                last_was_days = false;
+                i += 3;
+                continue;
+            }
+            i += 1;
+        }
+        for (start, end) in pending {
+            add_window(&mut windows, WEEK.to_vec(), start, end);
+        }
+
+        (!windows.is_empty()).then_some(Self { windows, timezone })
+    }
+
+    pub fn is_in_service(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.timezone);
+        let time = local.time().with_nanosecond(0).unwrap_or(local.time());
+        self.windows.iter().any(|w| w.covers(local.weekday(), time))
+    }
+
+    /// The first window start strictly after `after`, looking up to a week ahead.
+    pub fn next_service_start(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = after.with_timezone(&self.timezone).date_naive();
+        (0..=7)
+            .filter_map(|offset| today.checked_add_days(Days::new(offset)))
+            .flat_map(|date| {
+                self.windows
+                    .iter()
+                    .filter(move |w| w.days.contains(&date.weekday()))
+                    .filter_map(move |w| {
+                        // A start skipped by a DST change begins an hour later instead.
+                        let local = date.and_time(w.start);
+                        self.timezone
+                            .from_local_datetime(&local)
+                            .earliest()
+                            .or_else(|| {
+                                self.timezone
+                                    .from_local_datetime(&(local + chrono::Duration::hours(1)))
+                                    .earliest()
+                            })
+                    })
+            })
+            .map(|start| start.with_timezone(&Utc))
+            .filter(|start| *start > after)
+            .min()
+    }
+}
+
+impl RouteData {
+    /// Parsed `service_time`, falling back to `service_time_short`, in the route's
+    /// `timezone` (UTC when missing or unknown).
+    pub fn service_hours(&self) -> Option<ServiceHours> {
+        let timezone = self
+            .timezone
+            .as_deref()
+            .and_then(|tz| tz.trim().parse::<Tz>().ok())
+            .unwrap_or(Tz::UTC);
+        self.service_time
+            .iter()
+            .chain(self.service_time_short.iter())
+            .find_map(|text| ServiceHours::parse(text, timezone))
+    }
+
+    /// `None` when the route's service hours could not be parsed.
+    pub fn is_in_service(&self, at: DateTime<Utc>) -> Option<bool> {
+        Some(self.service_hours()?.is_in_service(at))
+    }
+
+    pub fn next_service_start(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.service_hours()?.next_service_start(after)
+    }
+}
+
+// Words are runs of letters, digits and `:`, with dots dropped so `a.m.` reads as `am`;
+// dashes, `to`, `thru` and `through` become `-` and `and` is dropped. A separate `am`/`pm`
+// joins the time before, and `noon`/`midnight` replace a bare `12` or `12:00` before them.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == ':' {
+            current.push(c);
+        } else if c == '.' {
+            continue;
+        } else {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            if matches!(c, '-' | '–' | '—') {
+                words.push("-".to_string());
+            }
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    let mut tokens: Vec<String> = Vec::new();
+    for word in words {
+        let word = match word.as_str() {
+            "to" | "thru" | "through" | "until" => "-".to_string(),
+            "and" => continue,
+            _ => word,
+        };
+        if (word == "noon" || word == "midnight")
+            && let Some(last) = tokens.last_mut()
+            && matches!(last.as_str(), "12" | "12:00")
+        {
+            *last = word;
+            continue;
+        }
+        if (word == "am" || word == "pm")
+            && let Some(last) = tokens.last_mut()
+            && last.starts_with(|c: char| c.is_ascii_digit())
+            && !last.ends_with("am")
+            && !last.ends_with("pm")
+        {
+            last.push_str(&word);
+            continue;
+        }
+        tokens.push(word);
+    }
+    tokens
+}
+
+fn add_window(
+    windows: &mut Vec<ServiceWindow>,
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+) {
+    match windows
+        .iter_mut()
+        .find(|w| w.start == start && w.end == end)
+    {
+        Some(window) => {
+            for day in days {
+                if !window.days.contains(&day) {
+                    window.days.push(day);
+                }
+            }
+        }
+        None => windows.push(ServiceWindow {
+            days,
+            start,
+            end,
+            overnight: end < start,
+        }),
+    }
+}
+
+// One- and two-letter abbreviations are also ordinary words (`we`, `su`), so they only count
+// as days next to another day, as in `M-F` or `M, W, F`.
+fn day_at(tokens: &[String], i: usize) -> Option<Vec<Weekday>> {
+    let word = tokens.get(i)?;
+    if let Some(days) = day_set(word) {
+        return Some(days);
+    }
+    let day = short_day(word)?;
+    let is_day = |j: usize| {
+        tokens
+            .get(j)
+            .is_some_and(|t| day_set(t).is_some() || short_day(t).is_some())
+    };
+    let dash = |j: usize| tokens.get(j).is_some_and(|t| t == "-");
+    let listed = (i > 0 && is_day(i - 1))
+        || is_day(i + 1)
+        || (dash(i + 1) && is_day(i + 2))
+        || (i > 1 && dash(i - 1) && is_day(i - 2));
+    listed.then(|| vec![day])
+}
+
+fn day_set(word: &str) -> Option<Vec<Weekday>> {
+    let day = match word.trim_end_matches(':') {
+        "daily" | "everyday" => return Some(WEEK.to_vec()),
+        "weekday" | "weekdays" => return Some(WEEK[..5].to_vec()),
+        "weekend" | "weekends" => return Some(WEEK[5..].to_vec()),
+        "mon" | "monday" | "mondays" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" | "tuesdays" => Weekday::Tue,
+        "wed" | "weds" | "wednesday" | "wednesdays" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" | "thursdays" => Weekday::Thu,
+        "fri" | "friday" | "fridays" => Weekday::Fri,
+        "sat" | "saturday" | "saturdays" => Weekday::Sat,
+        "sun" | "sunday" | "sundays" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(vec![day])
+}
+
+fn short_day(word: &str) -> Option<Weekday> {
+    Some(match word.trim_end_matches(':') {
+        "m" | "mo" => Weekday::Mon,
+        "tu" => Weekday::Tue,
+        "w" | "we" => Weekday::Wed,
+        "r" | "th" => Weekday::Thu,
+        "f" | "fr" => Weekday::Fri,
+        "sa" => Weekday::Sat,
+        "su" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// Seconds after midnight, and whether the word carried its own am/pm.
+fn clock(word: &str) -> Option<(u32, bool)> {
+    if !(word.starts_with(|c: char| c.is_ascii_digit()) || word == "noon" || word == "midnight") {
+        return None;
+    }
+    // `7a` and `7p` are short for `7am` and `7pm`.
+    let word = match word.strip_suffix(['a', 'p']) {
+        Some(rest) if rest.ends_with(|c: char| c.is_ascii_digit()) => format!("{}m", word),
+        _ => word.to_string(),
+    };
+    let has_meridiem = word.ends_with("am") || word.ends_with("pm");
+    Some((
+        parse_clock_time(&word)?,
+        has_meridiem || !word.starts_with(|c: char| c.is_ascii_digit()),
+    ))
+}
+
+// In `7-9am` or `10-2pm` the bare start borrows the end's half of the day when that keeps
+// it before the end, and takes the other half otherwise. A fully bare range that would end
+// before it starts, like `8-5`, runs from morning into the afternoon.
+fn resolve_meridiem(
+    (start, start_fixed): (u32, bool),
+    (end, end_fixed): (u32, bool),
+) -> (u32, u32) {
+    const HALF_DAY: u32 = 12 * 3600;
+    if !start_fixed && !end_fixed {
+        if end < start && start < HALF_DAY && (3600..HALF_DAY).contains(&end) {
+            return (start, end + HALF_DAY);
+        }
+        return (start, end);
+    }
+    if start_fixed || start >= HALF_DAY {
+        return (start, end);
+    }
+    let same_half = start % HALF_DAY + (end / HALF_DAY) * HALF_DAY;
+    if same_half <= end {
+        (same_half, end)
+    } else {
+        ((same_half + HALF_DAY) % (2 * HALF_DAY), end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEW_YORK: Tz = Tz::America__New_York;
+    const WEEKDAYS: &[Weekday] = &[
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ];
+
+    type Window = (Vec<Weekday>, NaiveTime, NaiveTime);
+
+    fn hm(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    fn windows(text: &str) -> Vec<Window> {
+        ServiceHours::parse(text, NEW_YORK)
+            .unwrap_or_else(|| panic!("no hours parsed from {:?}", text))
+            .windows
+            .into_iter()
+            .map(|w| (w.days, w.start, w.end))
+            .collect()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_service_time_strings() {
+        let cases: &[(&str, Vec<Window>)] = &[
+            (
+                "Mon-Fri 7:00am - 11:00pm; Sat & Sun 10am-2am",
+                vec![
+                    (WEEKDAYS.to_vec(), hm(7, 0), hm(23, 0)),
+                    (vec![Weekday::Sat, Weekday::Sun], hm(10, 0), hm(2, 0)),
+                ],
+            ),
+            (
+                "7:00a.m. to 6:00p.m. Monday through Friday",
+                vec![(WEEKDAYS.to_vec(), hm(7, 0), hm(18, 0))],
+            ),
+            ("M-F 7a-7p", vec![(WEEKDAYS.to_vec(), hm(7, 0), hm(19, 0))]),
+            (
+                "Weekdays 7-9am, 4-6pm",
+                vec![
+                    (WEEKDAYS.to_vec(), hm(7, 0), hm(9, 0)),
+                    (WEEKDAYS.to_vec(), hm(16, 0), hm(18, 0)),
+                ],
+            ),
+            (
+                "Monday - Thursday: 7:30 AM - 12:00 AM, Friday: 7:30 AM - 6:00 PM",
+                vec![
+                    (
+                        vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu],
+                        hm(7, 30),
+                        hm(0, 0),
+                    ),
+                    (vec![Weekday::Fri], hm(7, 30), hm(18, 0)),
+                ],
+            ),
+            (
+                "7:30am-10pm weekdays, 10am-6pm Sat",
+                vec![
+                    (WEEKDAYS.to_vec(), hm(7, 30), hm(22, 0)),
+                    (vec![Weekday::Sat], hm(10, 0), hm(18, 0)),
+                ],
+            ),
+            (
+                "Saturday 10am-6pm Sunday noon-6pm",
+                vec![
+                    (vec![Weekday::Sat], hm(10, 0), hm(18, 0)),
+                    (vec![Weekday::Sun], hm(12, 0), hm(18, 0)),
+                ],
+            ),
+            (
+                "Runs monthly, 8am-5pm",
+                vec![(WEEK.to_vec(), hm(8, 0), hm(17, 0))],
+            ),
+            (
+                "M-F: 7:00 AM to 12:00 midnight",
+                vec![(WEEKDAYS.to_vec(), hm(7, 0), hm(0, 0))],
+            ),
+            (
+                "Sa and Su 12 noon - 5pm",
+                vec![(vec![Weekday::Sat, Weekday::Sun], hm(12, 0), hm(17, 0))],
+            ),
+            (
+                "Mon, Wed, Fri 8-5",
+                vec![(
+                    vec![Weekday::Mon, Weekday::Wed, Weekday::Fri],
+                    hm(8, 0),
+                    hm(17, 0),
+                )],
+            ),
+            (
+                "M, W, F 7:30-11:30",
+                vec![(
+                    vec![Weekday::Mon, Weekday::Wed, Weekday::Fri],
+                    hm(7, 30),
+                    hm(11, 30),
+                )],
+            ),
+            ("22:00-02:00", vec![(WEEK.to_vec(), hm(22, 0), hm(2, 0))]),
+            ("We run 7am-5pm", vec![(WEEK.to_vec(), hm(7, 0), hm(17, 0))]),
+            ("24/7", vec![(WEEK.to_vec(), hm(0, 0), hm(0, 0))]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(&windows(text), expected, "{}", text);
+        }
+        assert_eq!(ServiceHours::parse("Call for hours", NEW_YORK), None);
+    }
+
+    #[test]
+    fn trailing_days_limit_service() {
+        let hours =
+            ServiceHours::parse("7:00a.m. to 6:00p.m. Monday through Friday", NEW_YORK).unwrap();
+        // Friday 2025-03-07 and Saturday 2025-03-08, noon local.
+        assert!(hours.is_in_service(utc("2025-03-07T17:00:00Z")));
+        assert!(!hours.is_in_service(utc("2025-03-08T17:00:00Z")));
+    }
+
+    #[test]
+    fn overnight_windows() {
+        let hours = ServiceHours::parse("Fri 10pm-2am", NEW_YORK).unwrap();
+        assert!(hours.windows[0].overnight);
+        // Saturday 2025-03-08 at 1am and 3am local (EST).
+        assert!(hours.is_in_service(utc("2025-03-08T06:00:00Z")));
+        assert!(!hours.is_in_service(utc("2025-03-08T08:00:00Z")));
+        assert_eq!(
+            hours.next_service_start(utc("2025-03-08T06:00:00Z")),
+            Some(utc("2025-03-15T02:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn next_service_start_across_dst() {
+        let hours = ServiceHours::parse("Daily 7am-7pm", NEW_YORK).unwrap();
+        // Clocks spring forward on 2025-03-09: 7am is 12:00Z the day before, 11:00Z after.
+        assert_eq!(
+            hours.next_service_start(utc("2025-03-08T20:00:00Z")),
+            Some(utc("2025-03-09T11:00:00Z"))
+        );
+        // And fall back on 2025-11-02.
+        assert_eq!(
+            hours.next_service_start(utc("2025-11-01T20:00:00Z")),
+            Some(utc("2025-11-02T12:00:00Z"))
+        );
+
+        // 2:30am does not exist on 2025-03-09, so service starts at 3:30am EDT.
+        let hours = ServiceHours::parse("Daily 2:30am-5am", NEW_YORK).unwrap();
+        assert_eq!(
+            hours.next_service_start(utc("2025-03-09T05:00:00Z")),
+            Some(utc("2025-03-09T07:30:00Z"))
+        );
+        // 1:30am happens twice on 2025-11-02; the first (EDT) one counts.
+        let hours = ServiceHours::parse("Daily 1:30am-5am", NEW_YORK).unwrap();
+        assert_eq!(
+            hours.next_service_start(utc("2025-11-02T04:00:00Z")),
+            Some(utc("2025-11-02T05:30:00Z"))
+        );
+    }
+}
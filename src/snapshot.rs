@@ -0,0 +1,436 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde_json::Value;
+
+use crate::helpers::{csv_escape, parse_timestamp};
+use crate::types::VehicleData;
+
+const CSV_COLUMNS: [&str; 17] = [
+    "fetched_at",
+    "system_id",
+    "id",
+    "name",
+    "type",
+    "calculated_course",
+    "route_id",
+    "route_name",
+    "color",
+    "created",
+    "latitude",
+    "longitude",
+    "speed",
+    "pax_load",
+    "out_of_service",
+    "more",
+    "trip_id",
+];
+
+#[derive(Debug, Clone)]
+pub struct VehicleSnapshot {
+    pub fetched_at: DateTime<Utc>,
+    pub system_id: i64,
+    pub vehicle: VehicleData,
+}
+
+impl VehicleSnapshot {
+    pub fn from_fetch(
+        system_id: i64,
+        fetched_at: DateTime<Utc>,
+        vehicles: Vec<VehicleData>,
+    ) -> Vec<VehicleSnapshot> {
+        vehicles
+            .into_iter()
+            .map(|vehicle| VehicleSnapshot {
+                fetched_at,
+                system_id,
+                vehicle,
+            })
+            .collect()
+    }
+}
+
+impl VehicleData {
+    /// When Passio last received this vehicle's position, from `created`. Times without an
+    /// offset are read in `timezone`, normally the route's.
+    pub fn reported_at(&self, timezone: Tz) -> Option<DateTime<Utc>> {
+        parse_timestamp(self.created.as_deref()?, timezone)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Csv,
+    JsonLines,
+}
+
+impl SnapshotFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            SnapshotFormat::Csv => "csv",
+            SnapshotFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+struct OpenFile {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    day: NaiveDate,
+    seq: u32,
+    bytes: u64,
+}
+
+/// Appends `get_buses` results to `{prefix}-{date}-{seq}.{csv,jsonl}` files in `dir`.
+///
+/// Files roll over at UTC midnight (unless disabled with `rotate_daily(false)`) and once they
+/// reach `max_bytes`. A single snapshot is never split across files, so a file can end up
+/// one snapshot larger than the limit.
+pub struct SnapshotWriter {
+    dir: PathBuf,
+    prefix: String,
+    format: SnapshotFormat,
+    max_bytes: Option<u64>,
+    rotate_daily: bool,
+    current: Option<OpenFile>,
+}
+
+impl SnapshotWriter {
+    pub fn new(dir: impl Into<PathBuf>, prefix: impl Into<String>, format: SnapshotFormat) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: prefix.into(),
+            format,
+            max_bytes: None,
+            rotate_daily: true,
+            current: None,
+        }
+    }
+
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn rotate_daily(mut self, rotate_daily: bool) -> Self {
+        self.rotate_daily = rotate_daily;
+        self
+    }
+
+    pub fn current_path(&self) -> Option<&Path> {
+        self.current.as_ref().map(|f| f.path.as_path())
+    }
+
+    pub fn write(
+        &mut self,
+        system_id: i64,
+        fetched_at: DateTime<Utc>,
+        vehicles: &[VehicleData],
+    ) -> io::Result<()> {
+        let day = fetched_at.date_naive();
+        let needs_new_file = match &self.current {
+            None => true,
+            Some(f) => {
+                (self.rotate_daily && f.day != day)
+                    || self.max_bytes.is_some_and(|max| f.bytes >= max)
+            }
+        };
+        if needs_new_file {
+            self.open_next(day)?;
+        }
+
+        let format = self.format;
+        let Some(file) = self.current.as_mut() else {
+            return Ok(());
+        };
+        let timestamp = fetched_at.to_rfc3339_opts(SecondsFormat::Millis, true);
+        for vehicle in vehicles {
+            let line = match format {
+                SnapshotFormat::Csv => csv_row(&timestamp, system_id, vehicle),
+                SnapshotFormat::JsonLines => json_row(&timestamp, system_id, vehicle)?,
+            };
+            file.writer.write_all(line.as_bytes())?;
+            file.writer.write_all(b"\n")?;
+            file.bytes += line.len() as u64 + 1;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.current.as_mut() {
+            Some(f) => f.writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn open_next(&mut self, day: NaiveDate) -> io::Result<()> {
+        let mut seq = match self.current.take() {
+            Some(mut prev) => {
+                prev.writer.flush()?;
+                if prev.day == day || !self.rotate_daily {
+                    prev.seq + 1
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        };
+        fs::create_dir_all(&self.dir)?;
+
+        // Pick up where a previous run left off instead of clobbering its files.
+        loop {
+            let path = self.dir.join(format!(
+                "{}-{}-{:03}.{}",
+                self.prefix,
+                day.format("%Y-%m-%d"),
+                seq,
+                self.format.extension()
+            ));
+            let existing = match fs::metadata(&path) {
+                Ok(meta) => meta.len(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(e),
+            };
+            if self.max_bytes.is_some_and(|max| existing >= max) {
+                seq += 1;
+                continue;
+            }
+
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            let mut open = OpenFile {
+                writer: BufWriter::new(file),
+                path,
+                day,
+                seq,
+                bytes: existing,
+            };
+            if existing == 0 && self.format == SnapshotFormat::Csv {
+                let header = CSV_COLUMNS.join(",");
+                open.writer.write_all(header.as_bytes())?;
+                open.writer.write_all(b"\n")?;
+                open.bytes += header.len() as u64 + 1;
+            }
+            self.current = Some(open);
+            return Ok(());
+        }
+    }
+}
+
+impl Drop for SnapshotWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+fn json_row(timestamp: &str, system_id: i64, vehicle: &VehicleData) -> io::Result<String> {
+    let mut row = serde_json::Map::new();
+    row.insert("fetched_at".to_string(), timestamp.into());
+    row.insert("system_id".to_string(), system_id.into());
+    if let Value::Object(fields) = serde_json::to_value(vehicle)? {
+        row.extend(fields);
+    }
+    Ok(Value::Object(row).to_string())
+}
+
+fn csv_row(timestamp: &str, system_id: i64, v: &VehicleData) -> String {
+    let num = |f: Option<f64>| f.map(|f| f.to_string()).unwrap_or_default();
+    let cells = [
+        timestamp.to_string(),
+        system_id.to_string(),
+        v.id.clone(),
+        v.name.clone().unwrap_or_default(),
+        v.r#type.clone().unwrap_or_default(),
+        num(v.calculated_course),
+        v.route_id.clone().unwrap_or_default(),
+        v.route_name.clone().unwrap_or_default(),
+        v.color.clone().unwrap_or_default(),
+        v.created.clone().unwrap_or_default(),
+        num(v.latitude),
+        num(v.longitude),
+        num(v.speed),
+        num(v.pax_load),
+        v.out_of_service.map(|b| b.to_string()).unwrap_or_default(),
+        v.more.clone().unwrap_or_default(),
+        v.trip_id.clone().unwrap_or_default(),
+    ];
+    cells
+        .iter()
+        .map(|c| csv_escape(c))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, removed when dropped.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "passiogo-snapshot-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            ScratchDir(dir)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn bus(id: &str) -> VehicleData {
+        VehicleData {
+            id: id.to_string(),
+            route_id: Some("R1".to_string()),
+            latitude: Some(41.0),
+            longitude: Some(-87.0),
+            ..Default::default()
+        }
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(2025, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn rotates_at_utc_midnight() {
+        let scratch = ScratchDir::new("daily");
+        let mut writer = SnapshotWriter::new(&scratch.0, "buses", SnapshotFormat::Csv);
+        writer.write(1, at(1, 10), &[bus("a")]).unwrap();
+        writer.write(1, at(1, 23), &[bus("b")]).unwrap();
+        writer.write(1, at(2, 0), &[bus("c")]).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            files(&scratch.0),
+            ["buses-2025-03-01-000.csv", "buses-2025-03-02-000.csv"]
+        );
+        let first = lines(&scratch.0.join("buses-2025-03-01-000.csv"));
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0], CSV_COLUMNS.join(","));
+        let second = lines(&scratch.0.join("buses-2025-03-02-000.csv"));
+        assert_eq!(second.len(), 2);
+        assert!(second[1].contains(",c,"));
+    }
+
+    #[test]
+    fn stays_in_one_file_without_daily_rotation() {
+        let scratch = ScratchDir::new("nodaily");
+        let mut writer =
+            SnapshotWriter::new(&scratch.0, "buses", SnapshotFormat::JsonLines).rotate_daily(false);
+        writer.write(1, at(1, 23), &[bus("a")]).unwrap();
+        writer.write(1, at(2, 1), &[bus("b")]).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(files(&scratch.0), ["buses-2025-03-01-000.jsonl"]);
+        let rows = lines(&scratch.0.join("buses-2025-03-01-000.jsonl"));
+        assert_eq!(rows.len(), 2);
+        let row: Value = serde_json::from_str(&rows[1]).unwrap();
+        assert_eq!(row["id"], "b");
+        assert_eq!(row["system_id"], 1);
+    }
+
+    #[test]
+    fn rotates_once_max_bytes_is_reached() {
+        let scratch = ScratchDir::new("size");
+        // Small enough that every snapshot overflows the file it lands in.
+        let mut writer =
+            SnapshotWriter::new(&scratch.0, "buses", SnapshotFormat::JsonLines).max_bytes(10);
+        writer.write(1, at(1, 10), &[bus("a"), bus("b")]).unwrap();
+        writer.write(1, at(1, 11), &[bus("c")]).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            files(&scratch.0),
+            ["buses-2025-03-01-000.jsonl", "buses-2025-03-01-001.jsonl"]
+        );
+        // The first snapshot is kept whole even though it overshoots the limit.
+        assert_eq!(
+            lines(&scratch.0.join("buses-2025-03-01-000.jsonl")).len(),
+            2
+        );
+        assert_eq!(
+            writer.current_path(),
+            Some(scratch.0.join("buses-2025-03-01-001.jsonl").as_path())
+        );
+    }
+
+    #[test]
+    fn resumes_without_clobbering_earlier_files() {
+        let scratch = ScratchDir::new("resume");
+        {
+            let mut writer = SnapshotWriter::new(&scratch.0, "buses", SnapshotFormat::Csv);
+            writer.write(1, at(1, 10), &[bus("a")]).unwrap();
+        }
+        {
+            let mut writer = SnapshotWriter::new(&scratch.0, "buses", SnapshotFormat::Csv);
+            writer.write(1, at(1, 11), &[bus("b")]).unwrap();
+        }
+
+        assert_eq!(files(&scratch.0), ["buses-2025-03-01-000.csv"]);
+        let rows = lines(&scratch.0.join("buses-2025-03-01-000.csv"));
+        // Appended to, with the header written only once.
+        assert_eq!(rows.len(), 3);
+        assert!(rows[1].contains(",a,"));
+        assert!(rows[2].contains(",b,"));
+    }
+
+    #[test]
+    fn resume_skips_files_already_at_the_limit() {
+        let scratch = ScratchDir::new("resume-full");
+        {
+            let mut writer =
+                SnapshotWriter::new(&scratch.0, "buses", SnapshotFormat::Csv).max_bytes(10);
+            writer.write(1, at(1, 10), &[bus("a")]).unwrap();
+        }
+        let full = fs::read_to_string(scratch.0.join("buses-2025-03-01-000.csv")).unwrap();
+        {
+            let mut writer =
+                SnapshotWriter::new(&scratch.0, "buses", SnapshotFormat::Csv).max_bytes(10);
+            writer.write(1, at(1, 11), &[bus("b")]).unwrap();
+        }
+
+        assert_eq!(
+            files(&scratch.0),
+            ["buses-2025-03-01-000.csv", "buses-2025-03-01-001.csv"]
+        );
+        assert_eq!(
+            fs::read_to_string(scratch.0.join("buses-2025-03-01-000.csv")).unwrap(),
+            full
+        );
+        let rows = lines(&scratch.0.join("buses-2025-03-01-001.csv"));
+        assert_eq!(rows[0], CSV_COLUMNS.join(","));
+        assert!(rows[1].contains(",b,"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::PassioGoClient;
+use crate::error::ApiError;
+use crate::route_stops::{RouteStops, stops_for_route};
+use crate::types::{RouteData, StopData, SystemAlertData, VehicleData};
+
+/// Routes, stops, alerts and vehicles of one system fetched together, with indexes for
+/// joining them.
+///
+/// References to routes the system did not return are kept rather than dropped: such
+/// vehicles are listed by `unrouted_vehicles`, and `routes_at_stop` skips the unknown ids.
+/// Alerts without a route (or with route `0`) are system-wide and only appear in
+/// `system_alerts`.
+#[derive(Debug, Clone)]
+pub struct SystemSnapshot {
+    pub system_id: i64,
+    pub fetched_at: DateTime<Utc>,
+    pub routes: Vec<RouteData>,
+    pub stops: Vec<StopData>,
+    pub alerts: Vec<SystemAlertData>,
+    pub vehicles: Vec<VehicleData>,
+    route_index: HashMap<String, usize>,
+    stop_index: HashMap<String, usize>,
+    vehicle_index: HashMap<String, usize>,
+    vehicles_by_route: HashMap<String, Vec<usize>>,
+    alerts_by_route: HashMap<String, Vec<usize>>,
+}
+
+impl PassioGoClient {
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_system_snapshot(&self, system_id: i64) -> Result<SystemSnapshot, ApiError> {
+        let (routes, stops, alerts, vehicles) = tokio::try_join!(
+            self.get_routes(system_id),
+            self.get_stops(system_id),
+            self.get_alerts(system_id),
+            self.get_buses(system_id),
+        )?;
+        Ok(SystemSnapshot::new(
+            system_id,
+            Utc::now(),
+            routes,
+            stops,
+            alerts,
+            vehicles,
+        ))
+    }
+}
+
+impl SystemSnapshot {
+    pub fn new(
+        system_id: i64,
+        fetched_at: DateTime<Utc>,
+        routes: Vec<RouteData>,
+        stops: Vec<StopData>,
+        alerts: Vec<SystemAlertData>,
+        vehicles: Vec<VehicleData>,
+    ) -> Self {
+        let route_index = index_by(&routes, |r| r.id.clone());
+        let stop_index = index_by(&stops, |s| s.id.clone());
+        let vehicle_index = index_by(&vehicles, |v| v.id.clone());
+
+        let mut vehicles_by_route: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, vehicle) in vehicles.iter().enumerate() {
+            if let Some(route_id) = vehicle.route_id.as_deref().and_then(route_ref) {
+                vehicles_by_route
+                    .entry(route_id.to_string())
+                    .or_default()
+                    .push(i);
+            }
+        }
+
+        let mut alerts_by_route: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, alert) in alerts.iter().enumerate() {
+            if let Some(route_id) = alert.route_id.as_deref().and_then(route_ref) {
+                alerts_by_route
+                    .entry(route_id.to_string())
+                    .or_default()
+                    .push(i);
+            }
+        }
+
+        Self {
+            system_id,
+            fetched_at,
+            routes,
+            stops,
+            alerts,
+            vehicles,
+            route_index,
+            stop_index,
+            vehicle_index,
+            vehicles_by_route,
+            alerts_by_route,
+        }
+    }
+
+    pub fn route(&self, route_id: &str) -> Option<&RouteData> {
+        self.route_index.get(route_id).map(|&i| &self.routes[i])
+    }
+
+    pub fn stop(&self, stop_id: &str) -> Option<&StopData> {
+        self.stop_index.get(stop_id).map(|&i| &self.stops[i])
+    }
+
+    pub fn vehicle(&self, vehicle_id: &str) -> Option<&VehicleData> {
+        self.vehicle_index
+            .get(vehicle_id)
+            .map(|&i| &self.vehicles[i])
+    }
+
+    pub fn vehicles_on_route(&self, route_id: &str) -> Vec<&VehicleData> {
+        self.vehicles_by_route
+            .get(route_id)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.vehicles[i])
+            .collect()
+    }
+
+    /// Vehicles with no route, or a route the system did not return.
+    pub fn unrouted_vehicles(&self) -> Vec<&VehicleData> {
+        self.vehicles
+            .iter()
+            .filter(|v| {
+                v.route_id
+                    .as_deref()
+                    .and_then(route_ref)
+                    .is_none_or(|id| !self.route_index.contains_key(id))
+            })
+            .collect()
+    }
+
+    /// Routes serving a stop, in the order the system listed them.
+    pub fn routes_at_stop(&self, stop_id: &str) -> Vec<&RouteData> {
+        let Some(stop) = self.stop(stop_id) else {
+            return Vec::new();
+        };
+        let mut indexes: Vec<usize> = stop
+            .routes_and_positions
+            .keys()
+            .filter_map(|id| self.route_index.get(id).copied())
+            .collect();
+        indexes.sort_unstable();
+        indexes.into_iter().map(|i| &self.routes[i]).collect()
+    }
+
+    pub fn stops_for_route(&self, route_id: &str) -> RouteStops<'_> {
+        stops_for_route(&self.stops, route_id)
+    }
+
+    pub fn alerts_for_route(&self, route_id: &str) -> Vec<&SystemAlertData> {
+        self.alerts_by_route
+            .get(route_id)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.alerts[i])
+            .collect()
+    }
+
+    pub fn system_alerts(&self) -> Vec<&SystemAlertData> {
+        self.alerts
+            .iter()
+            .filter(|a| a.route_id.as_deref().and_then(route_ref).is_none())
+            .collect()
+    }
+}
+
+// Passio uses an empty string or `0` for "no route".
+fn route_ref(route_id: &str) -> Option<&str> {
+    let route_id = route_id.trim();
+    (!route_id.is_empty() && route_id != "0").then_some(route_id)
+}
+
+fn index_by<T>(items: &[T], key: impl Fn(&T) -> String) -> HashMap<String, usize> {
+    let mut index = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        index.entry(key(item)).or_insert(i);
+    }
+    index
+}
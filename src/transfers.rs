@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::geo::haversine_meters;
+use crate::helpers::csv_escape;
+use crate::types::StopData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transfer {
+    pub from_stop_id: String,
+    pub to_stop_id: String,
+    pub meters: f64,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferGraphBuilder {
+    max_distance: f64,
+    walking_speed: f64,
+    include_same_route: bool,
+}
+
+impl Default for TransferGraphBuilder {
+    fn default() -> Self {
+        Self {
+            max_distance: 400.0,
+            walking_speed: 1.3,
+            include_same_route: false,
+        }
+    }
+}
+
+impl TransferGraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Longest walk in meters, measured as a straight line between stops.
+    pub fn max_distance(mut self, meters: f64) -> Self {
+        self.max_distance = meters;
+        self
+    }
+
+    pub fn walking_speed(mut self, meters_per_second: f64) -> Self {
+        self.walking_speed = meters_per_second;
+        self
+    }
+
+    /// Also link stops whose routes are all served at the stop walked from. Off by
+    /// default, since riding beats walking between them.
+    pub fn include_same_route(mut self, include: bool) -> Self {
+        self.include_same_route = include;
+        self
+    }
+
+    pub fn build(&self, stops: &[StopData]) -> TransferGraph {
+        let located: Vec<(&StopData, f64, f64)> = stops
+            .iter()
+            .filter_map(|s| Some((s, s.latitude?, s.longitude?)))
+            .collect();
+
+        let mut transfers = Vec::new();
+        for &(from, from_lat, from_lon) in &located {
+            for &(to, to_lat, to_lon) in &located {
+                if from.id == to.id {
+                    continue;
+                }
+                let new_route = to
+                    .routes_and_positions
+                    .keys()
+                    .any(|r| !from.routes_and_positions.contains_key(r));
+                if !self.include_same_route && !new_route {
+                    continue;
+                }
+                let meters = haversine_meters(from_lat, from_lon, to_lat, to_lon);
+                if meters <= self.max_distance {
+                    transfers.push(Transfer {
+                        from_stop_id: from.id.clone(),
+                        to_stop_id: to.id.clone(),
+                        meters,
+                        time: Duration::from_secs_f64(meters / self.walking_speed.max(0.1)),
+                    });
+                }
+            }
+        }
+        TransferGraph::new(transfers)
+    }
+}
+
+/// Walking transfers between stops, grouped by the stop walked from and nearest first.
+#[derive(Debug, Clone, Default)]
+pub struct TransferGraph {
+    transfers: Vec<Transfer>,
+    by_stop: HashMap<String, Range<usize>>,
+}
+
+impl TransferGraph {
+    fn new(mut transfers: Vec<Transfer>) -> Self {
+        transfers.sort_by(|a, b| {
+            a.from_stop_id
+                .cmp(&b.from_stop_id)
+                .then(a.meters.total_cmp(&b.meters))
+        });
+        let mut by_stop: HashMap<String, Range<usize>> = HashMap::new();
+        for (i, transfer) in transfers.iter().enumerate() {
+            by_stop
+                .entry(transfer.from_stop_id.clone())
+                .or_insert(i..i)
+                .end = i + 1;
+        }
+        Self { transfers, by_stop }
+    }
+
+    pub fn from_stop(&self, stop_id: &str) -> &[Transfer] {
+        self.by_stop
+            .get(stop_id)
+            .map(|range| &self.transfers[range.clone()])
+            .unwrap_or_default()
+    }
+
+    pub fn walking_time(&self, from_stop_id: &str, to_stop_id: &str) -> Option<Duration> {
+        self.from_stop(from_stop_id)
+            .iter()
+            .find(|t| t.to_stop_id == to_stop_id)
+            .map(|t| t.time)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Transfer> {
+        self.transfers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.transfers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transfers.is_empty()
+    }
+
+    /// Writes a GTFS `transfers.txt`, with each walk as a timed transfer (type 2) whose
+    /// `min_transfer_time` is the walking time rounded up to whole seconds.
+    pub fn write_gtfs_transfers<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "from_stop_id,to_stop_id,transfer_type,min_transfer_time")?;
+        for t in &self.transfers {
+            writeln!(
+                w,
+                "{},{},2,{}",
+                csv_escape(&t.from_stop_id),
+                csv_escape(&t.to_stop_id),
+                t.time.as_secs_f64().ceil() as u64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(id: &str, meters_east: f64, routes: &[&str]) -> StopData {
+        StopData {
+            id: id.to_string(),
+            latitude: Some(41.0),
+            longitude: Some(-87.0 + meters_east / (111_195.0 * 41f64.to_radians().cos())),
+            routes_and_positions: routes.iter().map(|r| (r.to_string(), vec![0.0])).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn stops() -> Vec<StopData> {
+        vec![
+            stop("A", 0.0, &["1"]),
+            stop("B", 100.0, &["1"]),
+            stop("C", 200.0, &["1", "2"]),
+            stop("far", 1000.0, &["3"]),
+            StopData {
+                latitude: None,
+                ..stop("nowhere", 0.0, &["4"])
+            },
+        ]
+    }
+
+    fn pairs(graph: &TransferGraph) -> Vec<(&str, &str, f64)> {
+        graph
+            .iter()
+            .map(|t| {
+                (
+                    t.from_stop_id.as_str(),
+                    t.to_stop_id.as_str(),
+                    t.meters.round(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_links_stops_with_new_routes_by_default() {
+        let graph = TransferGraphBuilder::new().build(&stops());
+        assert_eq!(pairs(&graph), [("A", "C", 200.0), ("B", "C", 100.0)]);
+        assert_eq!(graph.from_stop("C"), []);
+        assert_eq!(graph.walking_time("B", "C").map(|t| t.as_secs()), Some(76));
+        assert_eq!(graph.walking_time("C", "B"), None);
+    }
+
+    #[test]
+    fn include_same_route_links_every_nearby_stop() {
+        let graph = TransferGraphBuilder::new()
+            .include_same_route(true)
+            .max_distance(150.0)
+            .walking_speed(1.0)
+            .build(&stops());
+        assert_eq!(
+            pairs(&graph),
+            [
+                ("A", "B", 100.0),
+                ("B", "A", 100.0),
+                ("B", "C", 100.0),
+                ("C", "B", 100.0),
+            ]
+        );
+        assert_eq!(
+            graph
+                .from_stop("B")
+                .iter()
+                .map(|t| t.time.as_secs())
+                .collect::<Vec<_>>(),
+            [100, 100]
+        );
+        assert_eq!(graph.len(), 4);
+    }
+
+    #[test]
+    fn writes_gtfs_transfers() {
+        let graph = TransferGraphBuilder::new().build(&[
+            stop("North, Gate", 0.0, &["1"]),
+            stop("South \"Lot\"", 131.0, &["2"]),
+        ]);
+        let mut out = Vec::new();
+        graph.write_gtfs_transfers(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "from_stop_id,to_stop_id,transfer_type,min_transfer_time\n\
+             \"North, Gate\",\"South \"\"Lot\"\"\",2,101\n\
+             \"South \"\"Lot\"\"\",\"North, Gate\",2,101\n"
+        );
+    }
+}
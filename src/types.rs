@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-#[derive(Default, Debug, Clone)]
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct TransportationSystemData {
     pub id: i64,
     pub name: Option<String>,
@@ -16,9 +19,11 @@ pub struct TransportationSystemData {
     pub go_support_email: Option<String>,
     pub go_shared_code: Option<i64>,
     pub go_authentication_type: Option<bool>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct RouteData {
     pub id: String,
     pub group_id: Option<String>,
@@ -40,9 +45,28 @@ pub struct RouteData {
     pub service_time: Option<String>,
     pub service_time_short: Option<String>,
     pub system_id: Option<i64>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Coordinate {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct RouteShapeData {
+    pub route_id: String,
+    pub system_id: i64,
+    pub segments: Vec<Vec<Coordinate>>,
+    /// Unmapped point fields, one array per key with a value for every point in
+    /// `segments` order.
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct StopData {
     pub id: String,
     pub routes_and_positions: HashMap<String, Vec<f64>>,
@@ -51,9 +75,11 @@ pub struct StopData {
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
     pub radius: Option<f64>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct SystemAlertData {
     pub id: String,
     pub system_id: Option<i64>,
@@ -83,9 +109,11 @@ pub struct SystemAlertData {
     pub from_f: Option<String>,
     pub from_ok: Option<bool>,
     pub to_ok: Option<bool>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct VehicleData {
     pub id: String,
     pub name: Option<String>,
@@ -102,9 +130,11 @@ pub struct VehicleData {
     pub out_of_service: Option<bool>,
     pub more: Option<String>,
     pub trip_id: Option<String>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct ETAData {
     pub bus_name: String,
     pub eta: String,
@@ -118,4 +148,6 @@ pub struct ETAData {
     pub schedule_time: Option<String>,
     pub schedule_times: Option<Vec<String>>,
     pub seconds_spent: i64,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }